@@ -15,7 +15,7 @@ fn main() {
                     ..default()
                 })
                 .set(ImagePlugin::default_nearest()),
-            CoveyOfWorldsPlugin,
+            CoveyOfWorldsPlugin::default(),
         ))
         .add_plugins(WorldInspectorPlugin::default())
         .add_systems(Startup, startup)
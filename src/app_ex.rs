@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::type_name;
+use thiserror::Error;
+
+use crate::assets::entity::EntityAsset;
+use crate::assets::project::ProjectAsset;
+use crate::assets::traits::LdtkAssetLoadEvent;
+use crate::components::int_grid_colliders::IntGridColliderFilter;
+use crate::components::int_grid_colliders::IntGridColliderRule;
+use crate::components::int_grid_colliders::IntGridColliderRules;
+use crate::components::int_grid_colliders::IntGridColliderShapes;
+use crate::components::snapshot::LdtkSnapshotComponent;
+use crate::components::snapshot::LdtkSnapshotComponents;
+use crate::defs::entity_definition::EntityDefinition;
+use crate::exports::field_instance::field_instances_to_json;
+use crate::system_params::project::LdtkProjectCommands;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Error)]
+pub enum LdtkAppExError {
+    #[error("Bad handle?")]
+    BadHandle,
+    #[error("Bad project iid?")]
+    BadProjectIid,
+    #[error("Bad entity definition uid?")]
+    BadEntityDefUid,
+    #[error("Unable to build {type_name} from fields of {identifier}@{iid}! {source}")]
+    BadFields {
+        type_name: &'static str,
+        identifier: String,
+        iid: String,
+        source: serde_json::Error,
+    },
+}
+
+pub trait LdtkEntity: Bundle {
+    fn from_ldtk(
+        entity_asset: &EntityAsset,
+        entity_definition: &EntityDefinition,
+        project_asset: &ProjectAsset,
+    ) -> Self;
+}
+
+pub trait LdtkAppEx {
+    // Deserializes the field instances of every LDtk entity with the given identifier into `T`
+    // with serde, and inserts it as a component on spawn and on every reload.
+    fn register_ldtk_fields<T>(&mut self, identifier: &str) -> &mut Self
+    where
+        T: Component + DeserializeOwned;
+
+    // Inserts the bundle built by `B::from_ldtk` on every LDtk entity with the given identifier,
+    // on spawn and on every reload.
+    fn register_ldtk_entity<B>(&mut self, identifier: &str) -> &mut Self
+    where
+        B: LdtkEntity;
+
+    // Generates IntGridColliders on every IntGrid layer with the given identifier, regenerating
+    // them whenever the layer's IntGrid or the IntGridColliderRules resource changes. Registering
+    // several for one layer keeps the shapes of each apart, in registration order.
+    fn register_int_grid_colliders(
+        &mut self,
+        layer_identifier: &str,
+        filter: IntGridColliderFilter,
+        shapes: IntGridColliderShapes,
+    ) -> &mut Self;
+
+    // Saves the component of LDtk entities with every LdtkSnapshot, and restores it from the
+    // snapshot when they spawn again. It is saved under key, which must stay the same for
+    // existing saves to load, and be unique.
+    fn register_ldtk_snapshot_component<T>(&mut self, key: &str) -> &mut Self
+    where
+        T: Component + Serialize + DeserializeOwned;
+}
+
+impl LdtkAppEx for App {
+    fn register_ldtk_fields<T>(&mut self, identifier: &str) -> &mut Self
+    where
+        T: Component + DeserializeOwned,
+    {
+        self.add_systems(Update, ldtk_fields_system::<T>(identifier.to_owned()))
+    }
+
+    fn register_ldtk_entity<B>(&mut self, identifier: &str) -> &mut Self
+    where
+        B: LdtkEntity,
+    {
+        self.add_systems(Update, ldtk_entity_system::<B>(identifier.to_owned()))
+    }
+
+    fn register_int_grid_colliders(
+        &mut self,
+        layer_identifier: &str,
+        filter: IntGridColliderFilter,
+        shapes: IntGridColliderShapes,
+    ) -> &mut Self {
+        self.init_resource::<IntGridColliderRules>();
+        self.world
+            .resource_mut::<IntGridColliderRules>()
+            .rules
+            .push(IntGridColliderRule {
+                layer_identifier: layer_identifier.to_owned(),
+                filter,
+                shapes,
+            });
+        self
+    }
+
+    fn register_ldtk_snapshot_component<T>(&mut self, key: &str) -> &mut Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.init_resource::<LdtkSnapshotComponents>();
+        let mut components = self.world.resource_mut::<LdtkSnapshotComponents>();
+        assert!(
+            components
+                .components
+                .iter()
+                .all(|component| component.key != key),
+            "LDtk snapshot component key {key} is already registered!"
+        );
+        components
+            .components
+            .push(LdtkSnapshotComponent::new::<T>(key));
+        self
+    }
+}
+
+fn ldtk_fields_system<T>(
+    identifier: String,
+) -> impl FnMut(Commands, EventReader<LdtkAssetLoadEvent<EntityAsset>>, Res<Assets<EntityAsset>>)
+where
+    T: Component + DeserializeOwned,
+{
+    move |mut commands: Commands,
+          mut events: EventReader<LdtkAssetLoadEvent<EntityAsset>>,
+          entity_assets: Res<Assets<EntityAsset>>| {
+        // One bad entity mustn't keep the rest from getting their fields
+        for LdtkAssetLoadEvent { entity, handle } in events.read() {
+            if let Err(e) =
+                insert_ldtk_fields::<T>(&mut commands, *entity, handle, &identifier, &entity_assets)
+            {
+                error!("{e}");
+            }
+        }
+    }
+}
+
+fn insert_ldtk_fields<T>(
+    commands: &mut Commands,
+    entity: Entity,
+    handle: &Handle<EntityAsset>,
+    identifier: &str,
+    entity_assets: &Assets<EntityAsset>,
+) -> Result<(), LdtkAppExError>
+where
+    T: Component + DeserializeOwned,
+{
+    let entity_asset = entity_assets.get(handle).ok_or(LdtkAppExError::BadHandle)?;
+
+    if entity_asset.identifier != identifier {
+        return Ok(());
+    }
+
+    let bad_fields = |source| LdtkAppExError::BadFields {
+        type_name: type_name::<T>(),
+        identifier: entity_asset.identifier.clone(),
+        iid: entity_asset.iid.clone(),
+        source,
+    };
+
+    let value = field_instances_to_json(&entity_asset.field_instances).map_err(bad_fields)?;
+
+    let component: T = serde_json::from_value(value).map_err(bad_fields)?;
+
+    commands.entity(entity).try_insert(component);
+
+    Ok(())
+}
+
+fn ldtk_entity_system<B>(
+    identifier: String,
+) -> impl FnMut(
+    Commands,
+    EventReader<LdtkAssetLoadEvent<EntityAsset>>,
+    LdtkProjectCommands,
+    Res<Assets<EntityAsset>>,
+)
+where
+    B: LdtkEntity,
+{
+    move |mut commands: Commands,
+          mut events: EventReader<LdtkAssetLoadEvent<EntityAsset>>,
+          project_commands: LdtkProjectCommands,
+          entity_assets: Res<Assets<EntityAsset>>| {
+        // One bad entity mustn't keep the rest from getting their bundles
+        for LdtkAssetLoadEvent { entity, handle } in events.read() {
+            if let Err(e) = insert_ldtk_entity::<B>(
+                &mut commands,
+                *entity,
+                handle,
+                &identifier,
+                &project_commands,
+                &entity_assets,
+            ) {
+                error!("{e}");
+            }
+        }
+    }
+}
+
+fn insert_ldtk_entity<B>(
+    commands: &mut Commands,
+    entity: Entity,
+    handle: &Handle<EntityAsset>,
+    identifier: &str,
+    project_commands: &LdtkProjectCommands,
+    entity_assets: &Assets<EntityAsset>,
+) -> Result<(), LdtkAppExError>
+where
+    B: LdtkEntity,
+{
+    let entity_asset = entity_assets.get(handle).ok_or(LdtkAppExError::BadHandle)?;
+
+    if entity_asset.identifier != identifier {
+        return Ok(());
+    }
+
+    let project_asset = project_commands
+        .with_iid(&entity_asset.project_iid)
+        .ok_or(LdtkAppExError::BadProjectIid)?;
+
+    let entity_definition = project_asset
+        .entity_defs
+        .get(&entity_asset.def_uid)
+        .ok_or(LdtkAppExError::BadEntityDefUid)?;
+
+    let bundle = B::from_ldtk(entity_asset, entity_definition, project_asset);
+
+    commands.entity(entity).try_insert(bundle);
+
+    Ok(())
+}
@@ -4,14 +4,21 @@ use bevy::sprite::Anchor;
 use thiserror::Error;
 
 use crate::assets::traits::LdtkAsset;
+use crate::assets::traits::LdtkAssetLoadEvent;
+use crate::assets::util::build_nine_slice_image;
+use crate::assets::util::build_repeated_tile_image;
+use crate::assets::util::BuildImageFromTilesError;
+use crate::components::entity_refs::EntityRefs;
+use crate::components::field_instances::FieldInstances;
 use crate::components::iid::Iid;
 use crate::components::tileset_rectangle::TilesetRectangle;
 use crate::components::traits::LdtkComponent;
+use crate::defs::tile_render_mode::TileRenderMode;
 use crate::exports::field_instance::FieldInstance;
 use crate::exports::field_instance::FieldInstanceValueParseError;
 use crate::ldtk;
+use crate::plugin::EntityTransformReload;
 use crate::system_params::project::LdtkProjectCommands;
-use crate::system_params::project::LdtkProjectCommandsEx;
 use crate::util::bevy_anchor_from_ldtk;
 use crate::util::bevy_color_from_ldtk;
 use crate::util::AnchorIntoError;
@@ -25,6 +32,8 @@ pub enum EntityAssetError {
     AnchorIntoError(#[from] AnchorIntoError),
     #[error(transparent)]
     FieldInstanceValueError(#[from] FieldInstanceValueParseError),
+    #[error(transparent)]
+    BuildImageFromTilesError(#[from] BuildImageFromTilesError),
     #[error("One world coord is Some(...) and the other is None!")]
     WorldCoordMixedOption,
     #[error("Bad handle?")]
@@ -37,8 +46,16 @@ pub enum EntityAssetError {
     MissingTilesetPath,
     #[error("Bad Tileset Path!")]
     BadTilesetPath,
+    #[error("Bad entity definition uid?")]
+    BadEntityDefUid,
+    #[error("Tileset image not loaded?")]
+    BadTilesetImage,
 }
 
+// The translation LDtk last gave the entity, to tell whether gameplay has moved it since.
+#[derive(Component, Debug)]
+pub(crate) struct LdtkTranslation(Vec3);
+
 #[derive(Asset, Debug, Reflect)]
 pub struct EntityAsset {
     pub grid: I64Vec2,
@@ -53,6 +70,8 @@ pub struct EntityAsset {
     pub size: Vec2,
     pub iid: String,
     pub location: Vec3,
+    pub layer_iid: String,
+    pub level_iid: String,
     // #[reflect(ignore)]
     pub(crate) project_iid: String,
 }
@@ -60,6 +79,8 @@ pub struct EntityAsset {
 impl EntityAsset {
     pub(crate) fn new(
         value: &ldtk::EntityInstance,
+        layer_iid: String,
+        level_iid: String,
         project_iid: String,
     ) -> Result<Self, EntityAssetError> {
         Ok(Self {
@@ -85,6 +106,8 @@ impl EntityAsset {
             size: (value.width as f32, value.height as f32).into(),
             iid: value.iid.clone(),
             location: (value.px[0] as f32, -value.px[1] as f32, 0.0).into(),
+            layer_iid,
+            level_iid,
             project_iid,
         })
     }
@@ -104,29 +127,28 @@ impl EntityAsset {
         >,
         mut removed_tile: RemovedComponents<TilesetRectangle>,
         entity_assets: Res<Assets<EntityAsset>>,
+        mut images: ResMut<Assets<Image>>,
     ) -> Result<(), EntityAssetError> {
-        for (entity, handle, tile, mut sprite) in query.iter_mut() {
+        for (entity, handle, tile, sprite) in query.iter_mut() {
             let entity_asset = entity_assets
                 .get(handle)
                 .ok_or(EntityAssetError::BadHandle)?;
 
             let project_asset = project_commands
-                .iter()
                 .with_iid(&entity_asset.project_iid)
                 .ok_or(EntityAssetError::BadIid)?;
 
+            let entity_definition = project_asset
+                .entity_defs
+                .get(&entity_asset.def_uid)
+                .ok_or(EntityAssetError::BadEntityDefUid)?;
+
             let tileset_definition = project_asset
                 .tileset_defs
                 .get(&tile.tileset_uid)
                 .ok_or(EntityAssetError::BadTilesetUid)?;
 
-            let custom_size = Some(tile.size);
-
-            let rect = Some(Rect::from_corners(tile.location, tile.location + tile.size));
-
-            let anchor = entity_asset.anchor;
-
-            let texture = project_asset
+            let tileset = project_asset
                 .tileset_assets
                 .get(
                     tileset_definition
@@ -137,6 +159,61 @@ impl EntityAsset {
                 .ok_or(EntityAssetError::BadTilesetPath)?
                 .clone();
 
+            let anchor = entity_asset.anchor;
+
+            // The entity's pivot, from the top left corner, in the range 0.0 to 1.0
+            let pivot = anchor.as_vec() * Vec2::new(1.0, -1.0) + 0.5;
+
+            let size = entity_asset.size;
+
+            let tile_rect = Rect::from_corners(tile.location, tile.location + tile.size);
+
+            // Crops the tile down to the visible size, keeping the part around the pivot
+            let cropped_rect = |visible: Vec2| {
+                let min = tile.location + (tile.size - visible) * pivot;
+                Rect::from_corners(min, min + visible)
+            };
+
+            let (texture, rect, custom_size) = match entity_definition.tile_render_mode {
+                TileRenderMode::Stretch => (tileset, Some(tile_rect), size),
+                TileRenderMode::FitInside => {
+                    let scale = (size / tile.size).min_element();
+                    (tileset, Some(tile_rect), tile.size * scale)
+                }
+                TileRenderMode::Cover => {
+                    let scale = (size / tile.size).max_element();
+                    (tileset, Some(cropped_rect(size / scale)), size)
+                }
+                TileRenderMode::FullSizeCropped => {
+                    let visible = tile.size.min(size);
+                    (tileset, Some(cropped_rect(visible)), visible)
+                }
+                TileRenderMode::FullSizeUncropped => (tileset, Some(tile_rect), tile.size),
+                TileRenderMode::Repeat => {
+                    let image = build_repeated_tile_image(
+                        images
+                            .get(&tileset)
+                            .ok_or(EntityAssetError::BadTilesetImage)?,
+                        tile,
+                        size.as_uvec2(),
+                    )?;
+                    (images.add(image), None, size)
+                }
+                TileRenderMode::NineSlice => {
+                    let image = build_nine_slice_image(
+                        images
+                            .get(&tileset)
+                            .ok_or(EntityAssetError::BadTilesetImage)?,
+                        tile,
+                        size.as_uvec2(),
+                        &entity_definition.nine_slice_borders,
+                    )?;
+                    (images.add(image), None, size)
+                }
+            };
+
+            let custom_size = Some(custom_size);
+
             if let Some(mut sprite) = sprite {
                 sprite.custom_size = custom_size;
                 sprite.rect = rect;
@@ -162,6 +239,50 @@ impl EntityAsset {
         });
         Ok(())
     }
+
+    // Sets the translation on spawn, and on reload as the EntityTransformReload policy allows.
+    pub(crate) fn entity_transform_system(
+        mut commands: Commands,
+        mut events: EventReader<LdtkAssetLoadEvent<EntityAsset>>,
+        mut query: Query<(&mut Transform, Option<&LdtkTranslation>)>,
+        entity_transform_reload: Res<EntityTransformReload>,
+        entity_assets: Res<Assets<EntityAsset>>,
+    ) -> Result<(), EntityAssetError> {
+        for LdtkAssetLoadEvent { entity, handle } in events.read() {
+            let entity_asset = entity_assets
+                .get(handle)
+                .ok_or(EntityAssetError::BadHandle)?;
+
+            let Ok((mut transform, ldtk_translation)) = query.get_mut(*entity) else {
+                commands.entity(*entity).try_insert((
+                    SpatialBundle::from_transform(Transform::from_translation(
+                        entity_asset.location,
+                    )),
+                    LdtkTranslation(entity_asset.location),
+                ));
+                continue;
+            };
+
+            let overwrite = match *entity_transform_reload {
+                EntityTransformReload::Always => true,
+                EntityTransformReload::IfUnchanged => ldtk_translation
+                    .is_some_and(|ldtk_translation| ldtk_translation.0 == transform.translation),
+                EntityTransformReload::Never => false,
+            };
+
+            if overwrite && transform.translation != entity_asset.location {
+                transform.translation = entity_asset.location;
+            }
+
+            if overwrite {
+                commands
+                    .entity(*entity)
+                    .try_insert(LdtkTranslation(entity_asset.location));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl LdtkAsset for EntityAsset {
@@ -190,32 +311,12 @@ impl LdtkComponent<EntityAsset> for Iid {
         _: &mut Query<&mut Iid>,
         asset: &EntityAsset,
     ) -> Result<(), crate::components::traits::LdtkComponentError> {
-        let component = Iid(asset.iid.clone());
+        let component = Iid::new(&asset.iid);
         commands.entity(entity).try_insert(component);
         Ok(())
     }
 }
 
-impl LdtkComponent<EntityAsset> for Transform {
-    fn do_assign(
-        commands: &mut Commands,
-        entity: Entity,
-        query: &mut Query<&mut Self>,
-        asset: &EntityAsset,
-    ) -> Result<(), crate::components::traits::LdtkComponentError> {
-        if let Ok(_transform) = query.get_mut(entity) {
-            // transform.translation = asset.location;
-        } else {
-            commands
-                .entity(entity)
-                .try_insert(SpatialBundle::from_transform(Transform::from_translation(
-                    asset.location,
-                )));
-        }
-        Ok(())
-    }
-}
-
 impl LdtkComponent<EntityAsset> for TilesetRectangle {
     fn do_assign(
         commands: &mut Commands,
@@ -239,3 +340,38 @@ impl LdtkComponent<EntityAsset> for TilesetRectangle {
         Ok(())
     }
 }
+
+impl LdtkComponent<EntityAsset> for FieldInstances {
+    fn do_assign(
+        commands: &mut Commands,
+        entity: Entity,
+        _: &mut Query<&mut Self>,
+        asset: &EntityAsset,
+    ) -> Result<(), crate::components::traits::LdtkComponentError> {
+        if asset.field_instances.is_empty() {
+            commands.entity(entity).remove::<FieldInstances>();
+        } else {
+            commands.entity(entity).try_insert(FieldInstances {
+                field_instances: asset.field_instances.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl LdtkComponent<EntityAsset> for EntityRefs {
+    fn do_assign(
+        commands: &mut Commands,
+        entity: Entity,
+        _: &mut Query<&mut Self>,
+        asset: &EntityAsset,
+    ) -> Result<(), crate::components::traits::LdtkComponentError> {
+        let component = EntityRefs::new(&asset.field_instances);
+        if component.refs.is_empty() {
+            commands.entity(entity).remove::<EntityRefs>();
+        } else {
+            commands.entity(entity).try_insert(component);
+        }
+        Ok(())
+    }
+}
@@ -6,16 +6,24 @@ use thiserror::Error;
 use crate::assets::entity::EntityAsset;
 use crate::assets::traits::LdtkAsset;
 use crate::assets::traits::LdtkAssetChildLoader;
+use crate::assets::traits::LdtkAssetLoadEvent;
 use crate::assets::util::build_image_from_tiles;
+use crate::assets::util::build_mesh_from_tiles;
 use crate::assets::util::create_tile_layer_mesh;
+use crate::assets::util::patch_tiles_mesh;
+use crate::assets::util::repaint_tiles_image;
 use crate::assets::util::BuildImageFromTilesError;
 use crate::components::iid::Iid;
+use crate::components::int_grid::IntGrid;
+use crate::components::parallax::Parallax;
+use crate::components::tiles::TileMeshQuads;
 use crate::components::tiles::Tiles;
+use crate::components::tiles::TilesDirtyRegions;
 use crate::components::traits::LdtkComponent;
 use crate::exports::tile_instance::TileInstance;
 use crate::ldtk;
+use crate::plugin::TileLayerRendering;
 use crate::system_params::project::LdtkProjectCommands;
-use crate::system_params::project::LdtkProjectCommandsEx;
 
 #[derive(Debug, Error)]
 pub enum LayerAssetError {
@@ -27,13 +35,19 @@ pub enum LayerAssetError {
     BadHandle,
     #[error("Bad Iid?")]
     BadIid,
+    #[error("Bad tileset uid?")]
+    BadTilesetUid,
+    #[error("Missing tileset path!")]
+    MissingTilesetPath,
     #[error("Bad tileset path?")]
     BadTilesetPath,
     #[error("Bad tileset handle?")]
     BadTilesetHandle,
+    #[error("Bad layer definition uid?")]
+    BadLayerDefUid,
 }
 
-#[derive(Clone, Copy, Debug, Reflect)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
 pub enum LayerType {
     IntGrid,
     Entities,
@@ -72,6 +86,8 @@ pub struct LayerAsset {
     pub layer_def_uid: i64,
     pub level_id: i64,
     pub override_tileset_uid: Option<i64>,
+    pub optional_rules: Vec<i64>,
+    pub seed: i64,
     pub location: Vec3,
     pub visible: bool,
 
@@ -79,10 +95,12 @@ pub struct LayerAsset {
     pub index: usize,
     #[reflect(ignore)]
     pub(crate) entity_handles: Vec<Handle<EntityAsset>>,
+    pub level_iid: String,
     pub(crate) project_iid: String,
 }
 
 impl LayerAsset {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         value: &ldtk::LayerInstance,
         index: usize,
@@ -90,6 +108,7 @@ impl LayerAsset {
         tiles: Vec<TileInstance>,
         entity_handles: Vec<Handle<EntityAsset>>,
         layer_separation: f32,
+        level_iid: String,
         project_iid: String,
     ) -> Result<Self, LayerAssetError> {
         Ok(Self {
@@ -106,6 +125,8 @@ impl LayerAsset {
             layer_def_uid: value.layer_def_uid,
             level_id: value.level_id,
             override_tileset_uid: value.override_tileset_uid,
+            optional_rules: value.optional_rules.clone(),
+            seed: value.seed,
             location: (
                 value.px_offset_x as f32,
                 -value.px_offset_y as f32,
@@ -116,35 +137,64 @@ impl LayerAsset {
             index,
             tiles,
             entity_handles,
+            level_iid,
             project_iid,
         })
     }
 
-    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     pub(crate) fn layer_tiles_system(
         mut commands: Commands,
         project_commands: LdtkProjectCommands,
-        query: Query<(Entity, &Handle<LayerAsset>, &Tiles), Changed<Tiles>>,
+        mut query: Query<
+            (
+                Entity,
+                &Handle<LayerAsset>,
+                &Tiles,
+                Option<&TilesDirtyRegions>,
+                Option<&Mesh2dHandle>,
+                Option<&Handle<ColorMaterial>>,
+                Option<&mut TileMeshQuads>,
+            ),
+            Changed<Tiles>,
+        >,
         mut removed_tiles: RemovedComponents<Tiles>,
         layer_assets: Res<Assets<LayerAsset>>,
+        tile_layer_rendering: Res<TileLayerRendering>,
         mut images: ResMut<Assets<Image>>,
         mut meshes: ResMut<Assets<Mesh>>,
         mut materials: ResMut<Assets<ColorMaterial>>,
     ) -> Result<(), LayerAssetError> {
-        for (entity, handle, tiles) in query.iter() {
+        for (entity, handle, tiles, dirty_regions, mesh_handle, material_handle, mesh_quads) in
+            query.iter_mut()
+        {
+            commands.entity(entity).remove::<TilesDirtyRegions>();
+
             let layer_asset = layer_assets.get(handle).ok_or(LayerAssetError::BadHandle)?;
 
             let project_asset = project_commands
-                .iter()
                 .with_iid(&layer_asset.project_iid)
                 .ok_or(LayerAssetError::BadIid)?;
 
-            let Some(tileset_rel_path) = layer_asset.tileset_rel_path.as_ref() else {
+            let Some(tileset_uid) = layer_asset
+                .override_tileset_uid
+                .or(layer_asset.tileset_def_uid)
+            else {
                 commands.entity(entity).remove::<Mesh2dHandle>();
                 commands.entity(entity).remove::<Handle<ColorMaterial>>();
-                return Ok(());
+                continue;
             };
 
+            let tileset_definition = project_asset
+                .tileset_defs
+                .get(&tileset_uid)
+                .ok_or(LayerAssetError::BadTilesetUid)?;
+
+            let tileset_rel_path = tileset_definition
+                .rel_path
+                .as_ref()
+                .ok_or(LayerAssetError::MissingTilesetPath)?;
+
             debug!("tileset_rel_path: {tileset_rel_path:?}");
 
             let tileset_handle = project_asset
@@ -152,26 +202,105 @@ impl LayerAsset {
                 .get(tileset_rel_path)
                 .ok_or(LayerAssetError::BadTilesetPath)?;
 
+            // After an edit, composited images only redraw the regions it touched, and meshes
+            // only rewrite the quads of the locations in them.
+            if let (Some(dirty_regions), Some(mesh_handle), Some(material_handle)) =
+                (dirty_regions, mesh_handle, material_handle)
+            {
+                match *tile_layer_rendering {
+                    TileLayerRendering::Composited => {
+                        let image_handle = materials
+                            .get(material_handle)
+                            .and_then(|material| material.texture.clone());
+
+                        // The layer image shares Assets<Image> with its tileset, so its pixels are
+                        // taken out while the tileset is borrowed, and put back after.
+                        let image_pixels = image_handle
+                            .as_ref()
+                            .and_then(|image_handle| images.get_mut(image_handle))
+                            .map(|image| (image.size(), std::mem::take(&mut image.data)));
+
+                        if let (Some(image_handle), Some((image_size, mut image_data))) =
+                            (image_handle, image_pixels)
+                        {
+                            let repainted = images
+                                .get(tileset_handle)
+                                .ok_or(LayerAssetError::BadTilesetHandle)
+                                .and_then(|tileset| {
+                                    Ok(repaint_tiles_image(
+                                        &mut image_data,
+                                        image_size,
+                                        tileset,
+                                        tileset_definition,
+                                        tiles,
+                                        &dirty_regions.0,
+                                    )?)
+                                });
+
+                            if let Some(image) = images.get_mut(&image_handle) {
+                                image.data = image_data;
+                            }
+
+                            repainted?;
+                            continue;
+                        }
+                    }
+                    TileLayerRendering::Mesh => {
+                        let tileset = images
+                            .get(tileset_handle)
+                            .ok_or(LayerAssetError::BadTilesetHandle)?;
+
+                        if let (Some(mesh), Some(mut mesh_quads)) =
+                            (meshes.get_mut(&mesh_handle.0), mesh_quads)
+                        {
+                            if patch_tiles_mesh(
+                                mesh,
+                                &mut mesh_quads,
+                                tileset.size().as_vec2(),
+                                tileset_definition,
+                                tiles,
+                                &dirty_regions.0,
+                            ) {
+                                continue;
+                            }
+                        }
+                    }
+                }
+            }
+
             let tileset = images
                 .get(tileset_handle)
                 .ok_or(LayerAssetError::BadTilesetHandle)?;
 
-            debug!("making a canvas!");
-            let canvas_size = layer_asset.grid_size * layer_asset.grid_cell_size;
+            let color = Color::rgba(01.0, 1.0, 1.0, layer_asset.opacity as f32);
 
-            let mesh = create_tile_layer_mesh(canvas_size.as_vec2());
-            let mesh = Mesh2dHandle(meshes.add(mesh));
+            let (mesh, texture_handle) = match *tile_layer_rendering {
+                TileLayerRendering::Composited => {
+                    debug!("making a canvas!");
+                    let canvas_size = layer_asset.grid_size * layer_asset.grid_cell_size;
 
-            let image = build_image_from_tiles(
-                tileset,
-                canvas_size.as_uvec2(),
-                UVec2::splat(layer_asset.grid_cell_size as u32),
-                tiles,
-            )?;
+                    let mesh = create_tile_layer_mesh(canvas_size.as_vec2());
 
-            let color = Color::rgba(01.0, 1.0, 1.0, layer_asset.opacity as f32);
+                    let image = build_image_from_tiles(
+                        tileset,
+                        tileset_definition,
+                        canvas_size.as_uvec2(),
+                        tiles,
+                    )?;
 
-            let texture_handle = images.add(image);
+                    (mesh, images.add(image))
+                }
+                TileLayerRendering::Mesh => {
+                    let mesh =
+                        build_mesh_from_tiles(tileset.size().as_vec2(), tileset_definition, tiles);
+
+                    commands.entity(entity).insert(TileMeshQuads::new(tiles));
+
+                    (mesh, tileset_handle.clone())
+                }
+            };
+
+            let mesh = Mesh2dHandle(meshes.add(mesh));
 
             let texture = Some(texture_handle);
 
@@ -185,11 +314,84 @@ impl LayerAsset {
                 .entity(entity)
                 .remove::<Handle<Image>>()
                 .remove::<Handle<Mesh>>()
-                .remove::<Handle<ColorMaterial>>();
+                .remove::<Handle<ColorMaterial>>()
+                .remove::<TilesDirtyRegions>()
+                .remove::<TileMeshQuads>();
         });
 
         Ok(())
     }
+
+    pub(crate) fn int_grid_system(
+        mut commands: Commands,
+        mut events: EventReader<LdtkAssetLoadEvent<LayerAsset>>,
+        project_commands: LdtkProjectCommands,
+        layer_assets: Res<Assets<LayerAsset>>,
+    ) -> Result<(), LayerAssetError> {
+        for LdtkAssetLoadEvent { entity, handle } in events.read() {
+            let layer_asset = layer_assets.get(handle).ok_or(LayerAssetError::BadHandle)?;
+
+            if layer_asset.int_grid_csv.is_empty() {
+                commands.entity(*entity).remove::<IntGrid>();
+                continue;
+            }
+
+            let project_asset = project_commands
+                .with_iid(&layer_asset.project_iid)
+                .ok_or(LayerAssetError::BadIid)?;
+
+            let layer_definition = project_asset
+                .layer_defs
+                .get(&layer_asset.layer_def_uid)
+                .ok_or(LayerAssetError::BadLayerDefUid)?;
+
+            commands.entity(*entity).try_insert(IntGrid {
+                grid_size: layer_asset.grid_size,
+                grid_cell_size: layer_asset.grid_cell_size,
+                values: layer_asset.int_grid_csv.clone(),
+                int_grid_values: layer_definition.int_grid_values.clone(),
+                int_grid_values_groups: layer_definition.int_grid_values_groups.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn layer_parallax_system(
+        mut commands: Commands,
+        mut events: EventReader<LdtkAssetLoadEvent<LayerAsset>>,
+        project_commands: LdtkProjectCommands,
+        layer_assets: Res<Assets<LayerAsset>>,
+    ) -> Result<(), LayerAssetError> {
+        for LdtkAssetLoadEvent { entity, handle } in events.read() {
+            let layer_asset = layer_assets.get(handle).ok_or(LayerAssetError::BadHandle)?;
+
+            let project_asset = project_commands
+                .with_iid(&layer_asset.project_iid)
+                .ok_or(LayerAssetError::BadIid)?;
+
+            let layer_definition = project_asset
+                .layer_defs
+                .get(&layer_asset.layer_def_uid)
+                .ok_or(LayerAssetError::BadLayerDefUid)?;
+
+            let factor = Vec2::new(
+                layer_definition.parallax_factor_x as f32,
+                layer_definition.parallax_factor_y as f32,
+            );
+
+            if factor == Vec2::ZERO {
+                commands.entity(*entity).remove::<Parallax>();
+            } else {
+                commands.entity(*entity).try_insert(Parallax {
+                    factor,
+                    scaling: layer_definition.parallax_scaling,
+                });
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl LdtkAsset for LayerAsset {
@@ -225,7 +427,7 @@ impl LdtkComponent<LayerAsset> for Iid {
         _: &mut Query<&mut Self>,
         asset: &LayerAsset,
     ) -> Result<(), crate::components::traits::LdtkComponentError> {
-        commands.entity(entity).try_insert(Iid(asset.iid.clone()));
+        commands.entity(entity).try_insert(Iid::new(&asset.iid));
         Ok(())
     }
 }
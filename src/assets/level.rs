@@ -12,8 +12,10 @@ use image::RgbaImage;
 use thiserror::Error;
 
 use crate::assets::layer::LayerAsset;
+use crate::assets::level_asset_loader::LevelAssetLoaderSettings;
 use crate::assets::traits::LdtkAsset;
 use crate::assets::traits::LdtkAssetChildLoader;
+use crate::components::field_instances::FieldInstances;
 use crate::components::iid::Iid;
 use crate::components::traits::LdtkComponent;
 use crate::exports::field_instance::{FieldInstance, FieldInstanceValueParseError};
@@ -22,7 +24,6 @@ use crate::exports::neighbors::Neighbour;
 use crate::exports::neighbors::NeighbourError;
 use crate::ldtk;
 use crate::system_params::project::LdtkProjectCommands;
-use crate::system_params::project::LdtkProjectCommandsEx;
 use crate::util::bevy_color_from_ldtk;
 use crate::util::ColorParseError;
 
@@ -64,6 +65,13 @@ pub struct LevelAsset {
     pub project_iid: String,
     // #[reflect(ignore)]
     pub(crate) layer_handles: Vec<Handle<LayerAsset>>,
+    // For externalLevels projects: the .ldtkl asset path, and the settings to load it with.
+    // Such levels have no layers of their own until loaded.
+    #[reflect(ignore)]
+    pub(crate) external_level: Option<(String, LevelAssetLoaderSettings)>,
+    // The .ldtkl file as loaded, for exporting back to LDtk. Only kept like ProjectAsset::source
+    #[reflect(ignore)]
+    pub(crate) source: Option<ldtk::Level>,
 }
 
 impl LevelAsset {
@@ -98,9 +106,21 @@ impl LevelAsset {
                 .into(),
             project_iid,
             layer_handles,
+            external_level: None,
+            source: None,
         })
     }
 
+    // In the project's space. Like layers, a level spans (x, y) to (x + w, y - h).
+    pub fn bounds(&self) -> Rect {
+        let top_left = self.location.truncate();
+        Rect::from_corners(top_left, top_left + Vec2::new(self.size.x, -self.size.y))
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.bounds().contains(point)
+    }
+
     pub(crate) fn level_bg_system(
         mut commands: Commands,
         mut events: EventReader<LdtkAssetLoadEvent<LevelAsset>>,
@@ -113,7 +133,6 @@ impl LevelAsset {
             let level_asset = level_assets.get(handle).ok_or(LevelAssetError::BadHandle)?;
 
             let project_asset = project_commands
-                .iter()
                 .with_iid(&level_asset.project_iid)
                 .ok_or(LevelAssetError::BadProjectIid)?;
 
@@ -256,7 +275,7 @@ impl LdtkComponent<LevelAsset> for Iid {
         _: &mut Query<&mut Self>,
         asset: &LevelAsset,
     ) -> Result<(), crate::components::traits::LdtkComponentError> {
-        commands.entity(entity).try_insert(Iid(asset.iid.clone()));
+        commands.entity(entity).try_insert(Iid::new(&asset.iid));
 
         Ok(())
     }
@@ -281,3 +300,21 @@ impl LdtkComponent<LevelAsset> for Transform {
         Ok(())
     }
 }
+
+impl LdtkComponent<LevelAsset> for FieldInstances {
+    fn do_assign(
+        commands: &mut Commands,
+        entity: Entity,
+        _: &mut Query<&mut Self>,
+        asset: &LevelAsset,
+    ) -> Result<(), crate::components::traits::LdtkComponentError> {
+        if asset.field_instances.is_empty() {
+            commands.entity(entity).remove::<FieldInstances>();
+        } else {
+            commands.entity(entity).try_insert(FieldInstances {
+                field_instances: asset.field_instances.clone(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::asset::AssetLoader;
+use bevy::asset::AsyncReadExt;
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::assets::level::LevelAsset;
+use crate::assets::level::LevelAssetError;
+use crate::assets::project_asset_loader::add_layer_assets;
+use crate::assets::project_asset_loader::ProjectAssetLoaderError;
+use crate::ldtk;
+use crate::ldtk::LdtkVersion;
+
+// External level files don't know which project or world they belong to, so the project passes
+// those along with its separations when the level is requested.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LevelAssetLoaderSettings {
+    pub project_iid: String,
+    pub world_identifier: String,
+    pub level_separation: f32,
+    pub layer_separation: f32,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum LevelAssetLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    LevelAssetError(#[from] LevelAssetError),
+    #[error(transparent)]
+    ProjectAssetLoaderError(#[from] ProjectAssetLoaderError),
+    #[error("Layer Instances is None in an external level file?")]
+    LayerInstancesIsNone,
+    #[error("Unsupported LDtk version! {0}")]
+    UnsupportedVersion(String),
+}
+
+#[derive(Default)]
+pub(crate) struct LevelAssetLoader {
+    pub(crate) keep_source: bool,
+}
+
+impl AssetLoader for LevelAssetLoader {
+    type Asset = LevelAsset;
+    type Settings = LevelAssetLoaderSettings;
+    type Error = LevelAssetLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        settings: &'a Self::Settings,
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            info!("Loading LDtk level file: {:?}", load_context.path());
+
+            let value: ldtk::Level = {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes).await?;
+                let mut value: serde_json::Value = serde_json::from_slice(&bytes)?;
+
+                let app_version = value["__header__"]["appVersion"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned();
+
+                LdtkVersion::new(&app_version)
+                    .ok_or(LevelAssetLoaderError::UnsupportedVersion(app_version))?
+                    .migrate_level(&mut value);
+
+                serde_json::from_value(value)?
+            };
+
+            let layer_instances = value
+                .layer_instances
+                .as_ref()
+                .ok_or(LevelAssetLoaderError::LayerInstancesIsNone)?;
+
+            // Labelled like the layers of embedded levels
+            let label = format!("{}/{}", settings.world_identifier, value.identifier);
+
+            let layer_handles = add_layer_assets(
+                load_context,
+                &label,
+                layer_instances,
+                settings.layer_separation,
+                &value.iid,
+                &settings.project_iid,
+            )?;
+
+            let mut asset = LevelAsset::new(
+                &value,
+                settings.project_iid.clone(),
+                settings.level_separation,
+                layer_handles,
+            )?;
+            asset.source = self.keep_source.then_some(value);
+            Ok(asset)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtkl"]
+    }
+}
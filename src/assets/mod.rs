@@ -1,6 +1,7 @@
 pub(crate) mod entity;
 pub(crate) mod layer;
 pub(crate) mod level;
+pub(crate) mod level_asset_loader;
 pub(crate) mod project;
 pub(crate) mod project_asset_loader;
 pub(crate) mod traits;
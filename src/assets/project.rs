@@ -11,6 +11,9 @@ use crate::defs::entity_definition::EntityDefinition;
 use crate::defs::enum_definition::EnumDefinition;
 use crate::defs::layer_definition::LayerDefinition;
 use crate::defs::tileset_definition::TilesetDefinition;
+use crate::exports::toc::TocEntry;
+use crate::exports::toc::TocInstance;
+use crate::ldtk;
 
 #[derive(Asset, Debug, Reflect)]
 pub struct ProjectAsset {
@@ -18,6 +21,7 @@ pub struct ProjectAsset {
     pub external_levels: bool,
     pub iid: String,
     pub json_version: String,
+    pub toc: Vec<TocEntry>,
     pub(crate) tileset_assets: HashMap<String, Handle<Image>>,
     pub(crate) background_assets: HashMap<String, Handle<Image>>,
     pub(crate) layer_defs: HashMap<i64, LayerDefinition>,
@@ -28,6 +32,45 @@ pub struct ProjectAsset {
     // pub(crate) self_handle: Handle<ProjectAsset>,
     #[reflect(ignore)]
     pub(crate) world_handles: Vec<Handle<WorldAsset>>,
+    // As loaded, for exporting back to LDtk. Only kept with CoveyOfWorldsPlugin::keep_ldtk_source
+    #[reflect(ignore)]
+    pub(crate) source: Option<ldtk::LdtkJson>,
+}
+
+impl ProjectAsset {
+    pub fn toc_entry(&self, identifier: &str) -> Option<&TocEntry> {
+        self.toc.iter().find(|entry| entry.identifier == identifier)
+    }
+
+    // Every instance of the given entity identifier, or nothing if it isn't exported to the toc
+    pub fn toc_instances(&self, identifier: &str) -> impl Iterator<Item = &TocInstance> {
+        self.toc_entry(identifier)
+            .into_iter()
+            .flat_map(|entry| entry.instances.iter())
+    }
+
+    // Dropping the handle of a project root, by removing it or despawning the root alone, takes its
+    // worlds with it. Their strong handles would otherwise keep the project loaded.
+    pub(crate) fn handle_removed_system(
+        mut commands: Commands,
+        mut removed_handles: RemovedComponents<Handle<ProjectAsset>>,
+        world_query: Query<(Entity, &Parent), With<Handle<WorldAsset>>>,
+        project_query: Query<(), (With<Iid>, Without<Handle<ProjectAsset>>)>,
+    ) {
+        for entity in removed_handles.read() {
+            debug!("Project handle removed: {entity:?}");
+
+            world_query
+                .iter()
+                .filter(|(_, parent)| parent.get() == entity)
+                .for_each(|(world_entity, _)| commands.entity(world_entity).despawn_recursive());
+
+            // The root itself is the user's, but it's no longer a project
+            if project_query.contains(entity) {
+                commands.entity(entity).remove::<Iid>();
+            }
+        }
+    }
 }
 
 impl LdtkAssetChildLoader<WorldAsset> for ProjectAsset {
@@ -67,7 +110,7 @@ impl LdtkComponent<ProjectAsset> for Iid {
         _: &mut Query<&mut Iid>,
         asset: &ProjectAsset,
     ) -> Result<(), LdtkComponentError> {
-        let component = Iid(asset.iid.clone());
+        let component = Iid::new(&asset.iid);
         commands.entity(entity).try_insert(component);
         Ok(())
     }
@@ -88,3 +131,54 @@ impl LdtkComponent<ProjectAsset> for Transform {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_project(world: &mut World) -> (Entity, Entity, Entity) {
+        let level = world.spawn(Iid::new("level")).id();
+        let ldtk_world = world
+            .spawn((Handle::<WorldAsset>::default(), Iid::new("world")))
+            .add_child(level)
+            .id();
+        let project = world
+            .spawn((Handle::<ProjectAsset>::default(), Iid::new("project")))
+            .add_child(ldtk_world)
+            .id();
+        (project, ldtk_world, level)
+    }
+
+    fn run_teardown(world: &mut World) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(ProjectAsset::handle_removed_system);
+        schedule.run(world);
+    }
+
+    #[test]
+    fn removing_handle_despawns_worlds() {
+        let mut world = World::new();
+        let (project, ldtk_world, level) = spawn_project(&mut world);
+        run_teardown(&mut world);
+        assert!(world.get_entity(ldtk_world).is_some());
+
+        world.entity_mut(project).remove::<Handle<ProjectAsset>>();
+        run_teardown(&mut world);
+
+        assert!(world.get_entity(ldtk_world).is_none());
+        assert!(world.get_entity(level).is_none());
+        assert!(world.get::<Iid>(project).is_none());
+    }
+
+    #[test]
+    fn despawning_root_despawns_worlds() {
+        let mut world = World::new();
+        let (project, ldtk_world, level) = spawn_project(&mut world);
+
+        world.despawn(project);
+        run_teardown(&mut world);
+
+        assert!(world.get_entity(ldtk_world).is_none());
+        assert!(world.get_entity(level).is_none());
+    }
+}
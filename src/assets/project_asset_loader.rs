@@ -1,8 +1,8 @@
 use bevy::asset::AssetLoader;
 use bevy::asset::AsyncReadExt;
+use bevy::asset::LoadContext;
 use bevy::asset::ReadAssetBytesError;
 use bevy::prelude::*;
-use futures_lite::future;
 use serde::Deserialize;
 use serde::Serialize;
 use std::path::Path;
@@ -16,6 +16,7 @@ use crate::assets::layer::LayerAssetError;
 use crate::assets::layer::LayerType;
 use crate::assets::level::LevelAsset;
 use crate::assets::level::LevelAssetError;
+use crate::assets::level_asset_loader::LevelAssetLoaderSettings;
 use crate::assets::project::ProjectAsset;
 use crate::assets::world::WorldAsset;
 use crate::defs::entity_definition::EntityDefinition;
@@ -25,7 +26,10 @@ use crate::defs::layer_definition::LayerDefinition;
 use crate::defs::layer_definition::LayerDefinitionFromError;
 use crate::defs::tileset_definition::TilesetDefinition;
 use crate::exports::tile_instance::TileInstance;
+use crate::exports::toc::TocEntry;
+use crate::exports::toc::TocError;
 use crate::ldtk;
+use crate::ldtk::LdtkVersion;
 use crate::util::bevy_color_from_ldtk;
 use crate::util::ldtk_path_to_asset_path;
 use crate::util::ColorParseError;
@@ -65,6 +69,8 @@ pub(crate) enum ProjectAssetLoaderError {
     LayerDefinitionFromError(#[from] LayerDefinitionFromError),
     #[error(transparent)]
     EntityDefinitionFromError(#[from] EntityDefinitionFromError),
+    #[error(transparent)]
+    TocError(#[from] TocError),
     #[error("Could not get project directory? {0}")]
     BadProjectDirectory(PathBuf),
     #[error("externalRelPath is None when external_levels is true?")]
@@ -79,10 +85,14 @@ pub(crate) enum ProjectAssetLoaderError {
     IntGridWithEntitiesOrGridTiles,
     #[error("Tiles Layer should only have grid tiles!")]
     TilesWithAutoLayerOrEntities,
+    #[error("Unsupported LDtk JSON version! {0}")]
+    UnsupportedVersion(String),
 }
 
 #[derive(Default)]
-pub(crate) struct ProjectAssetLoader;
+pub(crate) struct ProjectAssetLoader {
+    pub(crate) keep_source: bool,
+}
 
 impl AssetLoader for ProjectAssetLoader {
     type Asset = ProjectAsset;
@@ -110,7 +120,16 @@ impl AssetLoader for ProjectAssetLoader {
             let value: ldtk::LdtkJson = {
                 let mut bytes = Vec::new();
                 reader.read_to_end(&mut bytes).await?;
-                serde_json::from_slice(&bytes)?
+
+                let mut value: serde_json::Value = serde_json::from_slice(&bytes)?;
+
+                let json_version = value["jsonVersion"].as_str().unwrap_or_default().to_owned();
+
+                LdtkVersion::new(&json_version)
+                    .ok_or(ProjectAssetLoaderError::UnsupportedVersion(json_version))?
+                    .migrate_project(&mut value);
+
+                serde_json::from_value(value)?
             };
 
             let ldtk_worlds = if value.worlds.is_empty() {
@@ -123,17 +142,17 @@ impl AssetLoader for ProjectAssetLoader {
                         .ok_or(ProjectAssetLoaderError::ValueMissingInSingleWorld)?,
                     identifier: "World".into(),
                     iid: value.iid.clone(),
-                    levels: value.levels,
+                    levels: value.levels.clone(),
                     world_grid_height: value
                         .world_grid_height
                         .ok_or(ProjectAssetLoaderError::ValueMissingInSingleWorld)?,
                     world_grid_width: value
                         .world_grid_width
                         .ok_or(ProjectAssetLoaderError::ValueMissingInSingleWorld)?,
-                    world_layout: value.world_layout,
+                    world_layout: value.world_layout.clone(),
                 }]
             } else {
-                value.worlds
+                value.worlds.clone()
             };
 
             let world_handles = ldtk_worlds
@@ -143,7 +162,12 @@ impl AssetLoader for ProjectAssetLoader {
                         .levels
                         .iter()
                         .map(|ldtk_level| {
-                            let layer_handles = if value.external_levels {
+                            let label =
+                                format!("{}/{}", ldtk_world.identifier, ldtk_level.identifier);
+
+                            // External levels only carry their metadata here. Their layers are
+                            // loaded from the .ldtkl file once the level is selected.
+                            let (layer_handles, external_level) = if value.external_levels {
                                 let level_path = ldtk_level
                                     .external_rel_path
                                     .as_ref()
@@ -151,93 +175,42 @@ impl AssetLoader for ProjectAssetLoader {
                                 let level_path = Path::new(&level_path);
                                 let level_path =
                                     ldtk_path_to_asset_path(&base_directory, level_path);
-                                let bytes_result: Result<Vec<u8>, ProjectAssetLoaderError> =
-                                    future::block_on(async {
-                                        Ok(load_context.read_asset_bytes(level_path).await?)
-                                    });
-                                let level_json: ldtk::Level =
-                                    serde_json::from_slice(&bytes_result?)?;
-                                level_json
-                                    .layer_instances
-                                    .ok_or(ProjectAssetLoaderError::LayerInstancesIsNone)?
+                                let level_settings = LevelAssetLoaderSettings {
+                                    project_iid: value.iid.clone(),
+                                    world_identifier: ldtk_world.identifier.clone(),
+                                    level_separation: settings.level_separation,
+                                    layer_separation: settings.layer_separation,
+                                };
+                                (
+                                    vec![],
+                                    Some((
+                                        level_path.to_string_lossy().into_owned(),
+                                        level_settings,
+                                    )),
+                                )
                             } else {
-                                ldtk_level
+                                let layer_instances = ldtk_level
                                     .layer_instances
                                     .as_ref()
-                                    .ok_or(ProjectAssetLoaderError::LayerInstancesIsNone)?
-                                    .to_vec()
-                            }
-                            .iter()
-                            .rev()
-                            .enumerate()
-                            .map(|(index, ldtk_layer)| {
-                                let layer_type = LayerType::new(&ldtk_layer.layer_instance_type)?;
-                                let (tiles, entity_handles) = match layer_type {
-                                    LayerType::IntGrid | LayerType::Autolayer => {
-                                            if !ldtk_layer.grid_tiles.is_empty() || !ldtk_layer.entity_instances.is_empty()  {
-                                                return Err(ProjectAssetLoaderError::IntGridWithEntitiesOrGridTiles);
-                                            }
-
-                                            (ldtk_layer.auto_layer_tiles.iter().map(TileInstance::new).collect(), vec![])
-                                        },
-                                    LayerType::Tiles => {
-                                            if !ldtk_layer.auto_layer_tiles.is_empty()|| !ldtk_layer.entity_instances.is_empty()   {
-                                                return Err(ProjectAssetLoaderError::TilesWithAutoLayerOrEntities);
-                                            }
-
-                                            (ldtk_layer.grid_tiles.iter().map(TileInstance::new).collect(), vec![])
-                                        },
-
-                                    LayerType::Entities => {
-                                            if !ldtk_layer.auto_layer_tiles.is_empty() || !ldtk_layer.grid_tiles.is_empty() {
-                                                return Err(ProjectAssetLoaderError::EntityLayerWithTiles)
-                                            }
-
-                                            let entity_assets = ldtk_layer.entity_instances
-                                                .iter()
-                                                .map(|ldtk_entity| {
-                                                    let label = format!("{}/{}/{}/{}@{}",
-                                                        ldtk_world.identifier,
-                                                        ldtk_level.identifier,
-                                                        ldtk_layer.identifier,
-                                                        ldtk_entity.identifier,
-                                                        ldtk_entity.iid);
-                                                    let asset = EntityAsset::new(ldtk_entity, value.iid.clone())?;
-                                                    Ok(load_context.add_labeled_asset(label, asset))
-                                                })
-                                                .collect::<Result<Vec<_>, ProjectAssetLoaderError>>()?;
-
-                                            (vec![], entity_assets)
-                                        },
-                                };
-
-                                let label = format!(
-                                    "{}/{}/{}",
-                                    ldtk_world.identifier,
-                                    ldtk_level.identifier,
-                                    ldtk_layer.identifier
-                                );
-                                let asset = LayerAsset::new(
-                                    ldtk_layer,
-                                    index,
-                                    layer_type,
-                                    tiles,
-                                    entity_handles,
+                                    .ok_or(ProjectAssetLoaderError::LayerInstancesIsNone)?;
+                                let layer_handles = add_layer_assets(
+                                    load_context,
+                                    &label,
+                                    layer_instances,
                                     settings.layer_separation,
-                                    value.iid.clone(),
+                                    &ldtk_level.iid,
+                                    &value.iid,
                                 )?;
-                                Ok(load_context.add_labeled_asset(label, asset))
-                            })
-                            .collect::<Result<Vec<_>, ProjectAssetLoaderError>>()?;
+                                (layer_handles, None)
+                            };
 
-                            let label =
-                                format!("{}/{}", ldtk_world.identifier, ldtk_level.identifier);
-                            let asset = LevelAsset::new(
+                            let mut asset = LevelAsset::new(
                                 ldtk_level,
                                 value.iid.clone(),
                                 settings.level_separation,
                                 layer_handles,
                             )?;
+                            asset.external_level = external_level;
                             Ok(load_context.add_labeled_asset(label, asset))
                         })
                         .collect::<Result<Vec<_>, ProjectAssetLoaderError>>()?;
@@ -307,11 +280,27 @@ impl AssetLoader for ProjectAssetLoader {
                 .map(|enum_def| (enum_def.uid, enum_def))
                 .collect();
 
+            let toc = value
+                .toc
+                .iter()
+                .map(|entry| {
+                    let field_defs = value
+                        .defs
+                        .entities
+                        .iter()
+                        .find(|entity_def| entity_def.identifier == entry.identifier)
+                        .map(|entity_def| entity_def.field_defs.as_slice())
+                        .unwrap_or_default();
+                    TocEntry::new(entry, field_defs)
+                })
+                .collect::<Result<_, _>>()?;
+
             Ok(ProjectAsset {
                 bg_color: bevy_color_from_ldtk(&value.bg_color)?,
                 external_levels: value.external_levels,
-                iid: value.iid,
+                iid: value.iid.clone(),
                 json_version: value.json_version.clone(),
+                toc,
                 tileset_assets,
                 background_assets,
                 // background_assets: HashMap::default(),
@@ -320,6 +309,7 @@ impl AssetLoader for ProjectAssetLoader {
                 tileset_defs,
                 enum_defs,
                 world_handles,
+                source: self.keep_source.then_some(value),
             })
         })
     }
@@ -328,3 +318,98 @@ impl AssetLoader for ProjectAssetLoader {
         &["ldtk"]
     }
 }
+
+// Labels are "{label_prefix}/{layer}" and "{label_prefix}/{layer}/{entity}@{iid}"
+pub(crate) fn add_layer_assets(
+    load_context: &mut LoadContext,
+    label_prefix: &str,
+    layer_instances: &[ldtk::LayerInstance],
+    layer_separation: f32,
+    level_iid: &str,
+    project_iid: &str,
+) -> Result<Vec<Handle<LayerAsset>>, ProjectAssetLoaderError> {
+    layer_instances
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, ldtk_layer)| {
+            let layer_type = LayerType::new(&ldtk_layer.layer_instance_type)?;
+            let (tiles, entity_handles) = match layer_type {
+                LayerType::IntGrid | LayerType::Autolayer => {
+                    if !ldtk_layer.grid_tiles.is_empty() || !ldtk_layer.entity_instances.is_empty()
+                    {
+                        return Err(ProjectAssetLoaderError::IntGridWithEntitiesOrGridTiles);
+                    }
+
+                    (
+                        ldtk_layer
+                            .auto_layer_tiles
+                            .iter()
+                            .map(TileInstance::new)
+                            .collect(),
+                        vec![],
+                    )
+                }
+                LayerType::Tiles => {
+                    if !ldtk_layer.auto_layer_tiles.is_empty()
+                        || !ldtk_layer.entity_instances.is_empty()
+                    {
+                        return Err(ProjectAssetLoaderError::TilesWithAutoLayerOrEntities);
+                    }
+
+                    (
+                        ldtk_layer
+                            .grid_tiles
+                            .iter()
+                            .map(TileInstance::new)
+                            .collect(),
+                        vec![],
+                    )
+                }
+
+                LayerType::Entities => {
+                    if !ldtk_layer.auto_layer_tiles.is_empty() || !ldtk_layer.grid_tiles.is_empty()
+                    {
+                        return Err(ProjectAssetLoaderError::EntityLayerWithTiles);
+                    }
+
+                    let entity_assets = ldtk_layer
+                        .entity_instances
+                        .iter()
+                        .map(|ldtk_entity| {
+                            let label = format!(
+                                "{}/{}/{}@{}",
+                                label_prefix,
+                                ldtk_layer.identifier,
+                                ldtk_entity.identifier,
+                                ldtk_entity.iid
+                            );
+                            let asset = EntityAsset::new(
+                                ldtk_entity,
+                                ldtk_layer.iid.clone(),
+                                level_iid.to_owned(),
+                                project_iid.to_owned(),
+                            )?;
+                            Ok(load_context.add_labeled_asset(label, asset))
+                        })
+                        .collect::<Result<Vec<_>, ProjectAssetLoaderError>>()?;
+
+                    (vec![], entity_assets)
+                }
+            };
+
+            let label = format!("{}/{}", label_prefix, ldtk_layer.identifier);
+            let asset = LayerAsset::new(
+                ldtk_layer,
+                index,
+                layer_type,
+                tiles,
+                entity_handles,
+                layer_separation,
+                level_iid.to_owned(),
+                project_iid.to_owned(),
+            )?;
+            Ok(load_context.add_labeled_asset(label, asset))
+        })
+        .collect::<Result<Vec<_>, ProjectAssetLoaderError>>()
+}
@@ -3,6 +3,7 @@ use std::fmt::Debug;
 use thiserror::Error;
 
 use crate::components::iid::Iid;
+use crate::components::iid::IidMap;
 
 #[derive(Event)]
 pub struct LdtkAssetLoadEvent<T: LdtkAsset> {
@@ -20,6 +21,7 @@ where
         assets: Res<Assets<Self>>,
         asset_server: Res<AssetServer>,
         mut asset_event_writer: EventWriter<LdtkAssetLoadEvent<Self>>,
+        mut iid_map: ResMut<IidMap>,
     ) {
         for (entity, handle) in query.iter() {
             if asset_server.is_loaded_with_dependencies(handle) {
@@ -27,7 +29,11 @@ where
 
                 let self_asset = assets.get(handle).expect("bad handle?");
 
-                commands.entity(entity).insert(Iid(self_asset.iid()));
+                let iid = Iid::from(self_asset.iid());
+
+                iid_map.insert(iid.clone(), entity, handle);
+
+                commands.entity(entity).insert(iid);
 
                 asset_event_writer.send(LdtkAssetLoadEvent {
                     entity,
@@ -38,7 +44,7 @@ where
     }
 
     fn on_modified_system(
-        // mut commands: Commands,
+        mut commands: Commands,
         mut asset_event_reader: EventReader<AssetEvent<Self>>,
         mut asset_event_writer: EventWriter<LdtkAssetLoadEvent<Self>>,
         query: Query<(Entity, &Handle<Self>)>,
@@ -58,15 +64,13 @@ where
                         });
                     }
                 }
-                AssetEvent::Removed { id } => {
-                    debug!("AssetEvent::Removed: {id:?}");
+                // Anything spawned from a removed asset goes with it, children included
+                AssetEvent::Removed { id } | AssetEvent::Unused { id } => {
+                    debug!("AssetEvent::Removed/Unused: {id:?}");
 
-                    // for (entity, _) in query.iter().filter(|(_, handle)| handle.id() == *id) {
-                    //     commands.entity(entity).despawn();
-                    // }
-                }
-                AssetEvent::Unused { id } => {
-                    debug!("AssetEvent::Unused: {id:?}");
+                    for (entity, _) in query.iter().filter(|(_, handle)| handle.id() == *id) {
+                        commands.entity(entity).despawn_recursive();
+                    }
                 }
                 AssetEvent::LoadedWithDependencies { id } => {
                     debug!("AssetEvent::LoadedWithDependencies: {id:?}");
@@ -114,7 +118,7 @@ where
                     let child_asset = child_assets.get(*child).expect("bad handle?");
                     !children_query
                         .iter()
-                        .any(|(_, iid)| child_asset.iid() == iid.0)
+                        .any(|(_, iid)| child_asset.iid() == iid.as_str())
                 })
                 .for_each(|child| {
                     debug!("Spawning: {child:?}");
@@ -134,7 +138,7 @@ where
                 .filter(|(_, iid)| {
                     !children.iter().any(|child| {
                         let child_asset = child_assets.get(child).expect("bad handle?");
-                        child_asset.iid() == iid.0
+                        child_asset.iid() == iid.as_str()
                     })
                 })
                 .for_each(|(entity, _)| {
@@ -1,16 +1,29 @@
+use bevy::math::I64Vec2;
 use bevy::prelude::*;
 use bevy::render::mesh::Indices;
 use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::mesh::VertexAttributeValues;
 use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::TextureFormat;
+use bevy::utils::HashMap;
 use image::imageops::crop_imm;
 use image::imageops::flip_horizontal;
 use image::imageops::flip_vertical;
 use image::imageops::overlay;
 use image::ColorType;
 use image::DynamicImage;
+use image::GenericImageView;
+use image::ImageBuffer;
+use image::Rgba;
+use image::RgbaImage;
+use std::borrow::Cow;
 use thiserror::Error;
 
+use crate::components::tiles::TileMeshQuads;
 use crate::components::tiles::Tiles;
+use crate::components::tileset_rectangle::TilesetRectangle;
+use crate::defs::tileset_definition::TilesetDefinition;
+use crate::exports::tile_instance::TileInstance;
 
 #[derive(Debug, Error)]
 pub enum BuildImageFromTilesError {
@@ -19,14 +32,25 @@ pub enum BuildImageFromTilesError {
     // let mut tileset = tileset_image.clone().try_into_dynamic()?;
     #[error("try_into_dynamic Failed!")]
     TryIntoDynamicFailed,
+    #[error("Nine slice borders should be four numbers!")]
+    BadNineSliceBorders,
+    #[error("Layer image is not RGBA8?")]
+    BadLayerImage,
+    #[error("Tileset image is not RGBA8?")]
+    BadTilesetImage,
 }
 
+// Tiles are cropped at the tileset's own tile size, which can differ from the layer's grid size.
+// TileInstance::source already accounts for the tileset's padding and spacing, so cropping
+// exactly one tile never bleeds into its neighbours.
 pub(crate) fn build_image_from_tiles(
     tileset: &Image,
+    tileset_definition: &TilesetDefinition,
     canvas_size: UVec2,
-    tile_size: UVec2,
     tiles: &Tiles,
 ) -> Result<Image, BuildImageFromTilesError> {
+    let tile_size = UVec2::splat(tileset_definition.tile_grid_size as u32);
+
     let tileset = tileset
         .clone()
         .try_into_dynamic()
@@ -36,26 +60,9 @@ pub(crate) fn build_image_from_tiles(
 
     tiles.tiles.iter().for_each(|tile| {
         // trace!("Tile loaded! {tile:?}");
-        let mut cropped = crop_imm(
-            &tileset,
-            tile.source.x,
-            tile.source.y,
-            tile_size.x,
-            tile_size.y,
-        )
-        .to_image();
-
-        if tile.flip_h {
-            cropped = flip_horizontal(&cropped);
-        }
-
-        if tile.flip_v {
-            cropped = flip_vertical(&cropped);
-        }
-
         overlay(
             &mut dynamic_image,
-            &cropped,
+            &tile_image(&tileset, tile_size, tile),
             tile.location.x,
             tile.location.y,
         );
@@ -68,6 +75,482 @@ pub(crate) fn build_image_from_tiles(
     ))
 }
 
+// Redraws only the given regions of an image built by build_image_from_tiles: each region is
+// cleared, then every tile overlapping it is drawn again, clipped to the region. Takes the pixels
+// of the image rather than the image, as it usually lives in the same Assets as the tileset.
+pub(crate) fn repaint_tiles_image(
+    image_data: &mut [u8],
+    image_size: UVec2,
+    tileset: &Image,
+    tileset_definition: &TilesetDefinition,
+    tiles: &Tiles,
+    regions: &[IRect],
+) -> Result<(), BuildImageFromTilesError> {
+    let tile_size = UVec2::splat(tileset_definition.tile_grid_size as u32);
+
+    // Edits happen often, so RGBA8 tilesets are read in place instead of being converted
+    let tileset_data = match tileset.texture_descriptor.format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => Cow::Borrowed(&tileset.data),
+        _ => Cow::Owned(
+            tileset
+                .clone()
+                .try_into_dynamic()
+                .map_err(|_| BuildImageFromTilesError::TryIntoDynamicFailed)?
+                .into_rgba8()
+                .into_raw(),
+        ),
+    };
+
+    let tileset_size = tileset.size();
+
+    let tileset = ImageBuffer::<Rgba<u8>, _>::from_raw(
+        tileset_size.x,
+        tileset_size.y,
+        tileset_data.as_slice(),
+    )
+    .ok_or(BuildImageFromTilesError::BadTilesetImage)?;
+
+    let mut canvas = ImageBuffer::<Rgba<u8>, _>::from_raw(image_size.x, image_size.y, image_data)
+        .ok_or(BuildImageFromTilesError::BadLayerImage)?;
+
+    let canvas_rect = IRect::from_corners(IVec2::ZERO, image_size.as_ivec2());
+
+    for region in regions.iter().map(|region| region.intersect(canvas_rect)) {
+        if region.is_empty() {
+            continue;
+        }
+
+        for y in region.min.y..region.max.y {
+            for x in region.min.x..region.max.x {
+                canvas.put_pixel(x as u32, y as u32, Rgba([0, 0, 0, 0]));
+            }
+        }
+
+        for tile in tiles.tiles.iter() {
+            let location = tile.location.as_ivec2();
+            let clipped =
+                IRect::from_corners(location, location + tile_size.as_ivec2()).intersect(region);
+
+            if clipped.is_empty() {
+                continue;
+            }
+
+            let offset = (clipped.min - location).as_uvec2();
+            let size = clipped.size().as_uvec2();
+
+            let cropped = crop_imm(
+                &tile_image(&tileset, tile_size, tile),
+                offset.x,
+                offset.y,
+                size.x,
+                size.y,
+            )
+            .to_image();
+
+            overlay(
+                &mut canvas,
+                &cropped,
+                clipped.min.x as i64,
+                clipped.min.y as i64,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// The pixels a tile covers in its layer's image
+pub(crate) fn tile_rect(tileset_definition: &TilesetDefinition, tile: &TileInstance) -> IRect {
+    location_rect(tileset_definition, tile.location)
+}
+
+pub(crate) fn location_rect(tileset_definition: &TilesetDefinition, location: I64Vec2) -> IRect {
+    let location = location.as_ivec2();
+    IRect::from_corners(
+        location,
+        location + IVec2::splat(tileset_definition.tile_grid_size as i32),
+    )
+}
+
+fn tile_image(
+    tileset: &impl GenericImageView<Pixel = Rgba<u8>>,
+    tile_size: UVec2,
+    tile: &TileInstance,
+) -> RgbaImage {
+    let view = crop_imm(
+        tileset,
+        tile.source.x,
+        tile.source.y,
+        tile_size.x,
+        tile_size.y,
+    );
+    let mut cropped = RgbaImage::from_fn(view.width(), view.height(), |x, y| view.get_pixel(x, y));
+
+    if tile.flip_h {
+        cropped = flip_horizontal(&cropped);
+    }
+
+    if tile.flip_v {
+        cropped = flip_vertical(&cropped);
+    }
+
+    if tile.alpha < 1.0 {
+        cropped.pixels_mut().for_each(|pixel| {
+            pixel[3] = (pixel[3] as f32 * tile.alpha).round() as u8;
+        });
+    }
+
+    cropped
+}
+
+// Fills the whole canvas with copies of the tile, starting from the top left corner
+pub(crate) fn build_repeated_tile_image(
+    tileset: &Image,
+    tile: &TilesetRectangle,
+    size: UVec2,
+) -> Result<Image, BuildImageFromTilesError> {
+    let tileset = tileset
+        .clone()
+        .try_into_dynamic()
+        .map_err(|_| BuildImageFromTilesError::TryIntoDynamicFailed)?;
+
+    let location = tile.location.as_uvec2();
+    let tile_size = tile.size.as_uvec2();
+
+    let source = crop_imm(&tileset, location.x, location.y, tile_size.x, tile_size.y).to_image();
+
+    let mut canvas = RgbaImage::new(size.x, size.y);
+
+    repeat_into(&mut canvas, &source, UVec2::ZERO, size);
+
+    Ok(Image::from_dynamic(
+        canvas.into(),
+        true,
+        RenderAssetUsages::default(),
+    ))
+}
+
+// Borders are [top, right, bottom, left] in tile pixels, as in LDtk. Corners are drawn as is,
+// while the sides and center are repeated to fill the canvas.
+pub(crate) fn build_nine_slice_image(
+    tileset: &Image,
+    tile: &TilesetRectangle,
+    size: UVec2,
+    borders: &[i64],
+) -> Result<Image, BuildImageFromTilesError> {
+    let [top, right, bottom, left] = borders else {
+        return Err(BuildImageFromTilesError::BadNineSliceBorders);
+    };
+
+    let tileset = tileset
+        .clone()
+        .try_into_dynamic()
+        .map_err(|_| BuildImageFromTilesError::TryIntoDynamicFailed)?;
+
+    let location = tile.location.as_uvec2();
+    let tile_size = tile.size.as_uvec2();
+
+    // Borders can't be larger than half of either the tile or the canvas
+    let limit = tile_size.min(size) / 2;
+    let left = (*left as u32).min(limit.x);
+    let right = (*right as u32).min(limit.x);
+    let top = (*top as u32).min(limit.y);
+    let bottom = (*bottom as u32).min(limit.y);
+
+    // (offset, length) of each column and row, in the tile and on the canvas
+    let source_columns = [
+        (0, left),
+        (left, tile_size.x - left - right),
+        (tile_size.x - right, right),
+    ];
+    let source_rows = [
+        (0, top),
+        (top, tile_size.y - top - bottom),
+        (tile_size.y - bottom, bottom),
+    ];
+    let target_columns = [
+        (0, left),
+        (left, size.x - left - right),
+        (size.x - right, right),
+    ];
+    let target_rows = [
+        (0, top),
+        (top, size.y - top - bottom),
+        (size.y - bottom, bottom),
+    ];
+
+    let mut canvas = RgbaImage::new(size.x, size.y);
+
+    for row in 0..3 {
+        for column in 0..3 {
+            let (source_x, source_width) = source_columns[column];
+            let (source_y, source_height) = source_rows[row];
+            let (target_x, target_width) = target_columns[column];
+            let (target_y, target_height) = target_rows[row];
+
+            if source_width == 0 || source_height == 0 || target_width == 0 || target_height == 0 {
+                continue;
+            }
+
+            let source = crop_imm(
+                &tileset,
+                location.x + source_x,
+                location.y + source_y,
+                source_width,
+                source_height,
+            )
+            .to_image();
+
+            repeat_into(
+                &mut canvas,
+                &source,
+                UVec2::new(target_x, target_y),
+                UVec2::new(target_width, target_height),
+            );
+        }
+    }
+
+    Ok(Image::from_dynamic(
+        canvas.into(),
+        true,
+        RenderAssetUsages::default(),
+    ))
+}
+
+fn repeat_into(canvas: &mut RgbaImage, source: &RgbaImage, target: UVec2, target_size: UVec2) {
+    if source.width() == 0 || source.height() == 0 {
+        return;
+    }
+
+    let mut y = 0;
+    while y < target_size.y {
+        let height = source.height().min(target_size.y - y);
+
+        let mut x = 0;
+        while x < target_size.x {
+            let width = source.width().min(target_size.x - x);
+
+            let piece = crop_imm(source, 0, 0, width, height).to_image();
+
+            overlay(canvas, &piece, (target.x + x) as i64, (target.y + y) as i64);
+
+            x += width;
+        }
+
+        y += height;
+    }
+}
+
+// One quad per tile, in the layer's local coordinates, with UVs into the tileset texture. Flips
+// swap the UVs, and the tile's alpha goes into the vertex color.
+pub(crate) fn build_mesh_from_tiles(
+    tileset_size: Vec2,
+    tileset_definition: &TilesetDefinition,
+    tiles: &Tiles,
+) -> Mesh {
+    let mut buffers = TileMeshBuffers::with_capacity(tiles.tiles.len());
+
+    tiles.tiles.iter().for_each(|tile| {
+        let quad = buffers.push_quad();
+        buffers.set_quad(quad, Some(tile), tileset_size, tileset_definition);
+    });
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    buffers.put_back(&mut mesh);
+    mesh
+}
+
+// Rewrites the quads of every location inside the regions from the tiles there, reusing their
+// quads, then empty ones, before adding more. Returns false if the mesh wasn't built by
+// build_mesh_from_tiles, in which case it has to be rebuilt.
+pub(crate) fn patch_tiles_mesh(
+    mesh: &mut Mesh,
+    quads: &mut TileMeshQuads,
+    tileset_size: Vec2,
+    tileset_definition: &TilesetDefinition,
+    tiles: &Tiles,
+    regions: &[IRect],
+) -> bool {
+    let Some(mut buffers) = TileMeshBuffers::take(mesh) else {
+        return false;
+    };
+
+    let in_regions = |location: I64Vec2| {
+        let location = location.as_ivec2();
+        regions
+            .iter()
+            .any(|region| location.cmpge(region.min).all() && location.cmplt(region.max).all())
+    };
+
+    let mut patched = HashMap::<I64Vec2, Vec<&TileInstance>>::default();
+    for tile in tiles.tiles.iter().filter(|tile| in_regions(tile.location)) {
+        patched.entry(tile.location).or_default().push(tile);
+    }
+
+    let cleared = quads
+        .locations
+        .keys()
+        .filter(|location| in_regions(**location) && !patched.contains_key(*location))
+        .copied()
+        .collect::<Vec<_>>();
+
+    for location in cleared {
+        for quad in quads.locations.remove(&location).unwrap_or_default() {
+            buffers.set_quad(quad, None, tileset_size, tileset_definition);
+            quads.free.push(quad);
+        }
+    }
+
+    for (location, location_tiles) in patched {
+        let mut location_quads = quads.locations.remove(&location).unwrap_or_default();
+
+        while location_quads.len() > location_tiles.len() {
+            let quad = location_quads.pop().expect("more quads than tiles");
+            buffers.set_quad(quad, None, tileset_size, tileset_definition);
+            quads.free.push(quad);
+        }
+
+        while location_quads.len() < location_tiles.len() {
+            let quad = quads.free.pop().unwrap_or_else(|| buffers.push_quad());
+            location_quads.push(quad);
+        }
+
+        // Tiles stacked in one location keep their order
+        location_quads.sort_unstable();
+
+        for (quad, tile) in location_quads.iter().zip(location_tiles) {
+            buffers.set_quad(*quad, Some(tile), tileset_size, tileset_definition);
+        }
+
+        quads.locations.insert(location, location_quads);
+    }
+
+    buffers.put_back(mesh);
+    true
+}
+
+#[derive(Default)]
+struct TileMeshBuffers {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl TileMeshBuffers {
+    fn with_capacity(quads: usize) -> Self {
+        Self {
+            positions: Vec::with_capacity(quads * 4),
+            uvs: Vec::with_capacity(quads * 4),
+            colors: Vec::with_capacity(quads * 4),
+            indices: Vec::with_capacity(quads * 6),
+        }
+    }
+
+    fn take(mesh: &mut Mesh) -> Option<Self> {
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return None;
+        };
+        let Some(VertexAttributeValues::Float32x2(uvs)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            return None;
+        };
+        let Some(VertexAttributeValues::Float32x4(colors)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_COLOR)
+        else {
+            return None;
+        };
+        let Some(Indices::U32(indices)) = mesh.remove_indices() else {
+            return None;
+        };
+
+        Some(Self {
+            positions,
+            uvs,
+            colors,
+            indices,
+        })
+    }
+
+    fn put_back(self, mesh: &mut Mesh) {
+        mesh.insert_indices(Indices::U32(self.indices));
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+    }
+
+    // An empty quad at the end
+    fn push_quad(&mut self) -> u32 {
+        let index = self.positions.len() as u32;
+
+        self.positions.extend([[0.0; 3]; 4]);
+        self.uvs.extend([[0.0; 2]; 4]);
+        self.colors.extend([[0.0; 4]; 4]);
+        self.indices
+            .extend([index, index + 1, index + 2, index, index + 2, index + 3]);
+
+        index / 4
+    }
+
+    // No tile empties the quad, collapsing it to a point
+    fn set_quad(
+        &mut self,
+        quad: u32,
+        tile: Option<&TileInstance>,
+        tileset_size: Vec2,
+        tileset_definition: &TilesetDefinition,
+    ) {
+        let vertices = quad as usize * 4..quad as usize * 4 + 4;
+
+        let Some(tile) = tile else {
+            self.positions[vertices.clone()].fill([0.0; 3]);
+            self.uvs[vertices.clone()].fill([0.0; 2]);
+            self.colors[vertices].fill([0.0; 4]);
+            return;
+        };
+
+        let tile_size = Vec2::splat(tileset_definition.tile_grid_size as f32);
+
+        let min = Vec2::new(tile.location.x as f32, -tile.location.y as f32);
+        let max = min + Vec2::new(tile_size.x, -tile_size.y);
+
+        let uv_min = tile.source.as_vec2() / tileset_size;
+        let uv_max = (tile.source.as_vec2() + tile_size) / tileset_size;
+
+        let (left, right) = if tile.flip_h {
+            (uv_max.x, uv_min.x)
+        } else {
+            (uv_min.x, uv_max.x)
+        };
+
+        let (top, bottom) = if tile.flip_v {
+            (uv_max.y, uv_min.y)
+        } else {
+            (uv_min.y, uv_max.y)
+        };
+
+        self.positions[vertices.clone()].copy_from_slice(&[
+            [min.x, min.y, 0.0],
+            [max.x, min.y, 0.0],
+            [max.x, max.y, 0.0],
+            [min.x, max.y, 0.0],
+        ]);
+        self.uvs[vertices.clone()].copy_from_slice(&[
+            [left, top],
+            [right, top],
+            [right, bottom],
+            [left, bottom],
+        ]);
+        self.colors[vertices].fill([1.0, 1.0, 1.0, tile.alpha]);
+    }
+}
+
 pub(crate) fn create_tile_layer_mesh(size: Vec2) -> Mesh {
     Mesh::new(
         PrimitiveTopology::TriangleList,
@@ -88,3 +571,155 @@ pub(crate) fn create_tile_layer_mesh(size: Vec2) -> Mesh {
         vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::render_resource::Extent3d;
+    use bevy::render::render_resource::TextureDimension;
+
+    // A 4x1 tileset of 8 pixel tiles, each pixel unique
+    fn tileset() -> (Image, TilesetDefinition) {
+        let data = (0..32 * 8)
+            .flat_map(|index| [index as u8, (index / 8) as u8, 255 - index as u8, 200])
+            .collect();
+
+        let image = Image::new(
+            Extent3d {
+                width: 32,
+                height: 8,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+
+        let tileset_definition = TilesetDefinition {
+            grid_size: (4, 1).into(),
+            tile_grid_size: 8,
+            ..default()
+        };
+
+        (image, tileset_definition)
+    }
+
+    fn tile(tileset_definition: &TilesetDefinition, tile_id: i64, cell: I64Vec2) -> TileInstance {
+        TileInstance {
+            alpha: 1.0,
+            flip_h: tile_id % 2 == 1,
+            flip_v: false,
+            location: cell * 8,
+            source: tileset_definition.tile_source(tile_id),
+            tileset_id: tile_id,
+        }
+    }
+
+    #[test]
+    fn repainting_matches_a_full_build() {
+        let (tileset, tileset_definition) = tileset();
+        let canvas_size = UVec2::new(32, 16);
+
+        let mut tiles = Tiles {
+            tiles: (0..8)
+                .map(|index| {
+                    tile(
+                        &tileset_definition,
+                        index % 4,
+                        (index % 4, index / 4).into(),
+                    )
+                })
+                .collect(),
+        };
+
+        let mut image =
+            build_image_from_tiles(&tileset, &tileset_definition, canvas_size, &tiles).unwrap();
+
+        let edited = tile(&tileset_definition, 3, (1, 1).into());
+        let region = tile_rect(&tileset_definition, &edited);
+        tiles.tiles[5] = edited;
+
+        let image_size = image.size();
+        repaint_tiles_image(
+            &mut image.data,
+            image_size,
+            &tileset,
+            &tileset_definition,
+            &tiles,
+            &[region],
+        )
+        .unwrap();
+
+        let rebuilt =
+            build_image_from_tiles(&tileset, &tileset_definition, canvas_size, &tiles).unwrap();
+
+        assert_eq!(image.data, rebuilt.data);
+    }
+
+    // Every non-empty quad of a tile mesh, in a comparable order
+    fn drawn_quads(mesh: &Mesh) -> Vec<String> {
+        let mut mesh = mesh.clone();
+        let buffers = TileMeshBuffers::take(&mut mesh).unwrap();
+
+        let mut quads = (0..buffers.positions.len() / 4)
+            .filter(|quad| buffers.colors[quad * 4][3] != 0.0)
+            .map(|quad| {
+                let vertices = quad * 4..quad * 4 + 4;
+                format!(
+                    "{:?} {:?} {:?}",
+                    &buffers.positions[vertices.clone()],
+                    &buffers.uvs[vertices.clone()],
+                    &buffers.colors[vertices],
+                )
+            })
+            .collect::<Vec<_>>();
+        quads.sort();
+        quads
+    }
+
+    #[test]
+    fn patching_matches_a_full_build() {
+        let (tileset, tileset_definition) = tileset();
+        let tileset_size = tileset.size().as_vec2();
+
+        let mut tiles = Tiles {
+            tiles: (0..8)
+                .map(|index| {
+                    tile(
+                        &tileset_definition,
+                        index % 4,
+                        (index % 4, index / 4).into(),
+                    )
+                })
+                .collect(),
+        };
+
+        let mut mesh = build_mesh_from_tiles(tileset_size, &tileset_definition, &tiles);
+        let mut quads = TileMeshQuads::new(&tiles);
+
+        // Replaced, cleared and stacked, with the tiles reordered as SetTile does
+        let replaced = tile(&tileset_definition, 3, (1, 1).into());
+        let stacked = tile(&tileset_definition, 2, (3, 0).into());
+        let regions =
+            [&replaced, &tiles.tiles[0], &stacked].map(|tile| tile_rect(&tileset_definition, tile));
+
+        tiles
+            .tiles
+            .retain(|tile| tile.location != replaced.location && tile.location != I64Vec2::ZERO);
+        tiles.tiles.extend([replaced, stacked]);
+
+        assert!(patch_tiles_mesh(
+            &mut mesh,
+            &mut quads,
+            tileset_size,
+            &tileset_definition,
+            &tiles,
+            &regions,
+        ));
+
+        let rebuilt = build_mesh_from_tiles(tileset_size, &tileset_definition, &tiles);
+
+        assert_eq!(drawn_quads(&mesh), drawn_quads(&rebuilt));
+    }
+}
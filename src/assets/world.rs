@@ -70,7 +70,7 @@ impl LdtkComponent<WorldAsset> for Iid {
         _: &mut Query<&mut Self>,
         asset: &WorldAsset,
     ) -> Result<(), crate::components::traits::LdtkComponentError> {
-        let component = Iid(asset.iid.clone());
+        let component = Iid::new(&asset.iid);
         commands.entity(entity).try_insert(component);
         Ok(())
     }
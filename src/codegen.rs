@@ -0,0 +1,313 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::ldtk;
+use crate::ldtk::LdtkVersion;
+
+#[derive(Debug, Error)]
+pub enum LdtkCodegenError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    Fmt(#[from] std::fmt::Error),
+    #[error("Unsupported LDtk JSON version! {0}")]
+    UnsupportedVersion(String),
+    #[error("Identifiers collide once converted to Rust! {0}")]
+    DuplicateIdentifier(String),
+}
+
+// For build scripts: writes Rust code for the enums, entity and layer identifiers and named
+// IntGrid values of an .ldtk project to a file, usually in OUT_DIR, to be include!()d by the
+// game. Renaming something in LDtk then breaks the build instead of lookups at runtime.
+// Generated enums derive serde's Deserialize and Serialize, so they work as LDtk fields.
+pub fn generate_ldtk_code(
+    ldtk_path: impl AsRef<Path>,
+    out_path: impl AsRef<Path>,
+) -> Result<(), LdtkCodegenError> {
+    let ldtk_path = ldtk_path.as_ref();
+
+    println!("cargo:rerun-if-changed={}", ldtk_path.display());
+
+    let mut value: serde_json::Value = serde_json::from_slice(&std::fs::read(ldtk_path)?)?;
+
+    let json_version = value["jsonVersion"].as_str().unwrap_or_default().to_owned();
+
+    LdtkVersion::new(&json_version)
+        .ok_or(LdtkCodegenError::UnsupportedVersion(json_version))?
+        .migrate_project(&mut value);
+
+    let project: ldtk::LdtkJson = serde_json::from_value(value)?;
+
+    let file_name = ldtk_path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy())
+        .unwrap_or_default();
+
+    std::fs::write(out_path, ldtk_code(&project, &file_name)?)?;
+
+    Ok(())
+}
+
+fn ldtk_code(project: &ldtk::LdtkJson, file_name: &str) -> Result<String, LdtkCodegenError> {
+    let mut code = String::new();
+
+    writeln!(
+        code,
+        "// Generated by covey_of_worlds from {file_name}. Rebuild instead of editing."
+    )?;
+
+    let enum_defs = project
+        .defs
+        .enums
+        .iter()
+        .chain(&project.defs.external_enums);
+    let mut enum_names = Names::default();
+
+    for enum_def in enum_defs {
+        let enum_name = enum_names.add(upper_camel_case(&enum_def.identifier))?;
+
+        let mut variant_names = Names::default();
+        let variants = enum_def
+            .values
+            .iter()
+            .map(|value| Ok((variant_names.add(upper_camel_case(&value.id))?, &value.id)))
+            .collect::<Result<Vec<_>, LdtkCodegenError>>()?;
+
+        writeln!(code)?;
+        writeln!(
+            code,
+            "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ::serde::Deserialize, ::serde::Serialize)]"
+        )?;
+        writeln!(code, "pub enum {enum_name} {{")?;
+        for (variant, id) in &variants {
+            writeln!(code, "    #[serde(rename = {id:?})]")?;
+            writeln!(code, "    {variant},")?;
+        }
+        writeln!(code, "}}")?;
+
+        writeln!(code)?;
+        writeln!(code, "impl {enum_name} {{")?;
+        writeln!(code, "    pub const ALL: &'static [Self] = &[")?;
+        for (variant, _) in &variants {
+            writeln!(code, "        Self::{variant},")?;
+        }
+        writeln!(code, "    ];")?;
+        writeln!(code)?;
+        writeln!(code, "    pub fn identifier(self) -> &'static str {{")?;
+        writeln!(code, "        match self {{")?;
+        for (variant, id) in &variants {
+            writeln!(code, "            Self::{variant} => {id:?},")?;
+        }
+        writeln!(code, "        }}")?;
+        writeln!(code, "    }}")?;
+        writeln!(code)?;
+        writeln!(
+            code,
+            "    pub fn from_identifier(identifier: &str) -> Option<Self> {{"
+        )?;
+        writeln!(
+            code,
+            "        Self::ALL.iter().copied().find(|value| value.identifier() == identifier)"
+        )?;
+        writeln!(code, "    }}")?;
+        writeln!(code, "}}")?;
+    }
+
+    writeln!(code)?;
+    writeln!(code, "pub mod entities {{")?;
+    let mut const_names = Names::default();
+    for entity_def in &project.defs.entities {
+        let const_name = const_names.add(screaming_snake_case(&entity_def.identifier))?;
+        let identifier = &entity_def.identifier;
+        writeln!(code, "    pub const {const_name}: &str = {identifier:?};")?;
+    }
+    writeln!(code, "}}")?;
+
+    writeln!(code)?;
+    writeln!(code, "pub mod layers {{")?;
+    let mut const_names = Names::default();
+    for layer_def in &project.defs.layers {
+        let const_name = const_names.add(screaming_snake_case(&layer_def.identifier))?;
+        let identifier = &layer_def.identifier;
+        writeln!(code, "    pub const {const_name}: &str = {identifier:?};")?;
+    }
+    writeln!(code, "}}")?;
+
+    // Only named values get a constant
+    writeln!(code)?;
+    writeln!(code, "pub mod int_grid {{")?;
+    let mut mod_names = Names::default();
+    for layer_def in project
+        .defs
+        .layers
+        .iter()
+        .filter(|layer_def| !layer_def.int_grid_values.is_empty())
+    {
+        let mod_name = mod_names.add(snake_case(&layer_def.identifier))?;
+        writeln!(code, "    pub mod {mod_name} {{")?;
+        let mut const_names = Names::default();
+        for int_grid_value in &layer_def.int_grid_values {
+            let Some(identifier) = &int_grid_value.identifier else {
+                continue;
+            };
+            let const_name = const_names.add(screaming_snake_case(identifier))?;
+            let value = int_grid_value.value;
+            writeln!(code, "        pub const {const_name}: i64 = {value};")?;
+        }
+        writeln!(code, "    }}")?;
+    }
+    writeln!(code, "}}")?;
+
+    Ok(code)
+}
+
+// Names already used in one scope of the generated code
+#[derive(Default)]
+struct Names(BTreeSet<String>);
+
+impl Names {
+    fn add(&mut self, name: String) -> Result<String, LdtkCodegenError> {
+        if self.0.insert(name.clone()) {
+            Ok(name)
+        } else {
+            Err(LdtkCodegenError::DuplicateIdentifier(name))
+        }
+    }
+}
+
+// LDtk identifiers are letters, digits and underscores, and don't start with a digit
+fn words(identifier: &str) -> Vec<String> {
+    let mut words = Vec::<String>::new();
+    let mut previous: Option<char> = None;
+
+    for c in identifier.chars() {
+        if !c.is_ascii_alphanumeric() {
+            previous = None;
+            continue;
+        }
+
+        let new_word = match previous {
+            None => true,
+            Some(previous) => previous.is_ascii_lowercase() && c.is_ascii_uppercase(),
+        };
+
+        match words.last_mut() {
+            Some(word) if !new_word => word.push(c),
+            _ => words.push(c.to_string()),
+        }
+
+        previous = Some(c);
+    }
+
+    words
+}
+
+fn upper_camel_case(identifier: &str) -> String {
+    let name = words(identifier)
+        .iter()
+        .map(|word| word[..1].to_ascii_uppercase() + &word[1..])
+        .collect::<String>();
+
+    match name.as_str() {
+        "" | "Self" => format!("{name}_"),
+        _ => name,
+    }
+}
+
+fn screaming_snake_case(identifier: &str) -> String {
+    let name = words(identifier)
+        .iter()
+        .map(|word| word.to_ascii_uppercase())
+        .collect::<Vec<_>>()
+        .join("_");
+
+    match name.as_str() {
+        "" => "_".to_owned(),
+        _ => name,
+    }
+}
+
+fn snake_case(identifier: &str) -> String {
+    let name = words(identifier)
+        .iter()
+        .map(|word| word.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("_");
+
+    match name.as_str() {
+        "" | "crate" | "self" | "super" => format!("{name}_"),
+        "as" | "async" | "await" | "box" | "break" | "const" | "continue" | "dyn" | "else"
+        | "enum" | "extern" | "false" | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop"
+        | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "static" | "struct"
+        | "trait" | "true" | "type" | "unsafe" | "use" | "where" | "while" | "yield" => {
+            format!("r#{name}")
+        }
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = include_str!("../tests/fixtures/ldtk_1_5_3.ldtk");
+
+    #[test]
+    fn converts_identifiers() {
+        assert_eq!(upper_camel_case("Health_potion"), "HealthPotion");
+        assert_eq!(upper_camel_case("axeMan2"), "AxeMan2");
+        assert_eq!(upper_camel_case("Self"), "Self_");
+        assert_eq!(screaming_snake_case("Axe_Man"), "AXE_MAN");
+        assert_eq!(screaming_snake_case("AxeMan"), "AXE_MAN");
+        assert_eq!(screaming_snake_case("HP"), "HP");
+        assert_eq!(snake_case("GroundLayer"), "ground_layer");
+        assert_eq!(snake_case("type"), "r#type");
+    }
+
+    #[test]
+    fn generates_project_code() {
+        let mut project: ldtk::LdtkJson = serde_json::from_str(PROJECT).unwrap();
+        project.defs.enums[0].values = ["Health_potion", "Key"]
+            .iter()
+            .map(|id| {
+                serde_json::from_value(serde_json::json!({
+                    "__tileSrcRect": null,
+                    "color": 0,
+                    "id": id,
+                    "tileId": null,
+                    "tileRect": null,
+                }))
+                .unwrap()
+            })
+            .collect();
+
+        let code = ldtk_code(&project, "ldtk_1_5_3.ldtk").unwrap();
+
+        for line in [
+            "pub enum Enum {",
+            "    #[serde(rename = \"Health_potion\")]",
+            "    HealthPotion,",
+            "            Self::Key => \"Key\",",
+            "pub enum Enum2 {",
+            "    pub const AXE_MAN: &str = \"Axe_Man\";",
+            "    pub const LANCER: &str = \"Lancer\";",
+            "    pub const TREES: &str = \"Trees\";",
+            "    pub mod bridges {",
+            "        pub const BRIDGE: i64 = 1;",
+            "    pub mod ground {",
+            "        pub const GRASS: i64 = 3;",
+        ] {
+            assert!(code.lines().any(|code_line| code_line == line), "{line}");
+        }
+
+        project.defs.enums[1].identifier = "enum".to_owned();
+        assert!(matches!(
+            ldtk_code(&project, "ldtk_1_5_3.ldtk"),
+            Err(LdtkCodegenError::DuplicateIdentifier(name)) if name == "Enum"
+        ));
+    }
+}
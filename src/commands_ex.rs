@@ -0,0 +1,267 @@
+use bevy::ecs::system::Command;
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::assets::layer::LayerAsset;
+use crate::assets::layer::LayerType;
+use crate::assets::project::ProjectAsset;
+use crate::assets::util::location_rect;
+use crate::components::iid::Iid;
+use crate::components::iid::IidMap;
+use crate::components::int_grid::IntGrid;
+use crate::components::snapshot::LdtkSnapshot;
+use crate::components::tiles::Tiles;
+use crate::components::tiles::TilesDirtyRegions;
+use crate::exports::tile_instance::TileInstance;
+use crate::project_export::export_ldtk_project;
+
+pub trait LdtkCommandsEx {
+    // Despawns the project with the given iid, with all of its worlds, levels, layers and
+    // entities. The project asset unloads once nothing else holds a handle to it.
+    fn unload_ldtk_project(&mut self, iid: &str) -> &mut Self;
+
+    // Replaces whatever is in the given cell of a Tiles layer with a tile from the layer's
+    // tileset. Tile ids count left to right, then top to bottom, as in LDtk.
+    fn set_tile(&mut self, layer_iid: &str, grid: I64Vec2, tile_id: i64, flip: BVec2) -> &mut Self;
+
+    // Empties the given cell of a Tiles layer.
+    fn clear_tile(&mut self, layer_iid: &str, grid: I64Vec2) -> &mut Self;
+
+    // Sets the given cell of an IntGrid layer, 0 being empty. Colliders and auto-layer tiles
+    // built from the layer follow.
+    fn set_int_grid(&mut self, layer_iid: &str, grid: I64Vec2, value: i64) -> &mut Self;
+
+    // Writes the project with the given iid, as modified at runtime, to an .ldtk file at the
+    // given path. Loaded external levels are written next to it. See export_ldtk_project.
+    fn save_ldtk_project(&mut self, iid: &str, path: impl Into<PathBuf>) -> &mut Self;
+
+    // Records the transforms and registered components of spawned LDtk entities into the
+    // LdtkSnapshot resource. See LdtkSnapshot::take.
+    fn take_ldtk_snapshot(&mut self) -> &mut Self;
+}
+
+impl LdtkCommandsEx for Commands<'_, '_> {
+    fn unload_ldtk_project(&mut self, iid: &str) -> &mut Self {
+        self.add(UnloadLdtkProject {
+            iid: iid.to_owned(),
+        });
+        self
+    }
+
+    fn set_tile(&mut self, layer_iid: &str, grid: I64Vec2, tile_id: i64, flip: BVec2) -> &mut Self {
+        self.add(SetTile {
+            layer_iid: layer_iid.to_owned(),
+            grid,
+            tile: Some((tile_id, flip)),
+        });
+        self
+    }
+
+    fn clear_tile(&mut self, layer_iid: &str, grid: I64Vec2) -> &mut Self {
+        self.add(SetTile {
+            layer_iid: layer_iid.to_owned(),
+            grid,
+            tile: None,
+        });
+        self
+    }
+
+    fn set_int_grid(&mut self, layer_iid: &str, grid: I64Vec2, value: i64) -> &mut Self {
+        self.add(SetIntGrid {
+            layer_iid: layer_iid.to_owned(),
+            grid,
+            value,
+        });
+        self
+    }
+
+    fn save_ldtk_project(&mut self, iid: &str, path: impl Into<PathBuf>) -> &mut Self {
+        self.add(SaveLdtkProject {
+            iid: iid.to_owned(),
+            path: path.into(),
+        });
+        self
+    }
+
+    fn take_ldtk_snapshot(&mut self) -> &mut Self {
+        self.add(|world: &mut World| {
+            if let Err(e) = LdtkSnapshot::take(world) {
+                error!("Unable to take LDtk snapshot! {e}");
+            }
+        });
+        self
+    }
+}
+
+struct UnloadLdtkProject {
+    iid: String,
+}
+
+impl Command for UnloadLdtkProject {
+    fn apply(self, world: &mut World) {
+        let projects = world
+            .query_filtered::<(Entity, &Iid), With<Handle<ProjectAsset>>>()
+            .iter(world)
+            .filter(|(_, iid)| iid.as_str() == self.iid)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+
+        if projects.is_empty() {
+            warn!("No LDtk project with iid {} to unload!", self.iid);
+        }
+
+        for entity in projects {
+            debug!("Unloading project: {entity:?}");
+            despawn_with_children_recursive(world, entity);
+        }
+    }
+}
+
+// A None tile clears the cell
+struct SetTile {
+    layer_iid: String,
+    grid: I64Vec2,
+    tile: Option<(i64, BVec2)>,
+}
+
+impl Command for SetTile {
+    fn apply(self, world: &mut World) {
+        let Some(entity) = world.resource::<IidMap>().entity(&self.layer_iid) else {
+            warn!("No LDtk layer with iid {}!", self.layer_iid);
+            return;
+        };
+
+        let Some(layer_asset) = world
+            .get::<Handle<LayerAsset>>(entity)
+            .and_then(|handle| world.resource::<Assets<LayerAsset>>().get(handle))
+        else {
+            warn!("Layer {} is not loaded!", self.layer_iid);
+            return;
+        };
+
+        if layer_asset.layer_type != LayerType::Tiles {
+            warn!("Layer {} is not a Tiles layer!", self.layer_iid);
+            return;
+        }
+
+        if !(self.grid.cmpge(I64Vec2::ZERO).all() && self.grid.cmplt(layer_asset.grid_size).all()) {
+            warn!("{} is outside of layer {}!", self.grid, self.layer_iid);
+            return;
+        }
+
+        let Some(tileset_definition) = world
+            .resource::<IidMap>()
+            .handle::<ProjectAsset>(&layer_asset.project_iid)
+            .and_then(|handle| world.resource::<Assets<ProjectAsset>>().get(handle))
+            .and_then(|project_asset| {
+                let tileset_uid = layer_asset
+                    .override_tileset_uid
+                    .or(layer_asset.tileset_def_uid)?;
+                project_asset.tileset_defs.get(&tileset_uid)
+            })
+        else {
+            warn!("No tileset for layer {}!", self.layer_iid);
+            return;
+        };
+
+        let location = self.grid * layer_asset.grid_cell_size;
+
+        // Whatever is in the cell covers the same pixels
+        let region = location_rect(tileset_definition, location);
+
+        let tile = match self.tile {
+            Some((tile_id, _)) if !tileset_definition.contains_tile(tile_id) => {
+                warn!(
+                    "Tile {tile_id} is not in the tileset of layer {}!",
+                    self.layer_iid
+                );
+                return;
+            }
+            Some((tile_id, flip)) => Some(TileInstance {
+                alpha: 1.0,
+                flip_h: flip.x,
+                flip_v: flip.y,
+                location,
+                source: tileset_definition.tile_source(tile_id),
+                tileset_id: tile_id,
+            }),
+            None => None,
+        };
+
+        match world.get_mut::<Tiles>(entity) {
+            Some(mut tiles) => {
+                // Read through the Mut first, so that clearing an empty cell changes nothing
+                if tile.is_none() && tiles.tiles.iter().all(|tile| tile.location != location) {
+                    return;
+                }
+                tiles.tiles.retain(|tile| tile.location != location);
+                tiles.tiles.extend(tile);
+            }
+            None => {
+                let Some(tile) = tile else {
+                    return;
+                };
+                world.entity_mut(entity).insert(Tiles { tiles: vec![tile] });
+            }
+        }
+
+        TilesDirtyRegions::add(world, entity, region);
+    }
+}
+
+struct SetIntGrid {
+    layer_iid: String,
+    grid: I64Vec2,
+    value: i64,
+}
+
+impl Command for SetIntGrid {
+    fn apply(self, world: &mut World) {
+        let Some(entity) = world.resource::<IidMap>().entity(&self.layer_iid) else {
+            warn!("No LDtk layer with iid {}!", self.layer_iid);
+            return;
+        };
+
+        let Some(int_grid) = world.get::<IntGrid>(entity) else {
+            warn!("Layer {} has no IntGrid!", self.layer_iid);
+            return;
+        };
+
+        if self.value != 0 && int_grid.definition(self.value).is_none() {
+            warn!("{} is not a value of layer {}!", self.value, self.layer_iid);
+            return;
+        }
+
+        match int_grid.get(self.grid) {
+            None => warn!("{} is outside of layer {}!", self.grid, self.layer_iid),
+            Some(value) if value == self.value => (),
+            Some(_) => {
+                if let Some(mut int_grid) = world.get_mut::<IntGrid>(entity) {
+                    int_grid.set(self.grid, self.value);
+                }
+            }
+        }
+    }
+}
+
+struct SaveLdtkProject {
+    iid: String,
+    path: PathBuf,
+}
+
+impl Command for SaveLdtkProject {
+    fn apply(self, world: &mut World) {
+        let export = match export_ldtk_project(world, &self.iid) {
+            Ok(export) => export,
+            Err(e) => {
+                error!("Unable to export LDtk project {}! {e}", self.iid);
+                return;
+            }
+        };
+
+        if let Err(e) = export.write(&self.path) {
+            error!("Unable to write {}! {e}", self.path.display());
+        }
+    }
+}
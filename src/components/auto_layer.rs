@@ -0,0 +1,759 @@
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::utils::HashSet;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use crate::assets::layer::LayerAsset;
+use crate::assets::layer::LayerType;
+use crate::assets::level::LevelAsset;
+use crate::assets::traits::LdtkAssetLoadEvent;
+use crate::assets::util::tile_rect;
+use crate::components::int_grid::IntGrid;
+use crate::components::tiles::Tiles;
+use crate::components::tiles::TilesDirtyRegions;
+use crate::defs::auto_layer_rule::AutoLayerRule;
+use crate::defs::auto_layer_rule::AutoLayerRuleChecker;
+use crate::defs::auto_layer_rule::AutoLayerRuleTileMode;
+use crate::defs::layer_definition::LayerDefinition;
+use crate::defs::tileset_definition::TilesetDefinition;
+use crate::exports::field_instance::FieldInstance;
+use crate::exports::field_instance::FieldInstanceValue;
+use crate::exports::tile_instance::TileInstance;
+use crate::system_params::project::LdtkProjectCommands;
+
+#[derive(Debug, Error)]
+pub enum AutoLayerError {
+    #[error("Bad handle?")]
+    BadHandle,
+    #[error("Bad project iid?")]
+    BadProjectIid,
+    #[error("Bad layer definition uid?")]
+    BadLayerDefUid,
+    #[error("Bad tileset uid?")]
+    BadTilesetUid,
+    #[error("Layer parent is not a level?")]
+    ParentNotLevel,
+}
+
+// Pattern values matching any non-empty cell. Values above 999 otherwise match an IntGrid
+// value group, as (group uid + 1) * 1000.
+const ANYTHING: i64 = 1000001;
+
+// The rules of one layer, in the order LDtk evaluates them, along with what's needed to
+// place their tiles.
+pub(crate) struct AutoLayerRules<'a> {
+    rules: Vec<&'a AutoLayerRule>,
+    seed: i64,
+    grid_cell_size: i64,
+    tile_pivot: Vec2,
+    tileset_definition: &'a TilesetDefinition,
+}
+
+impl<'a> AutoLayerRules<'a> {
+    // Level fields hold the biome values deciding which rule groups apply
+    pub(crate) fn new(
+        layer_definition: &'a LayerDefinition,
+        layer_asset: &LayerAsset,
+        level_field_instances: &[FieldInstance],
+        tileset_definition: &'a TilesetDefinition,
+    ) -> Self {
+        let biome_values: Vec<String> = layer_definition
+            .biome_field_uid
+            .and_then(|biome_field_uid| {
+                level_field_instances
+                    .iter()
+                    .find(|field_instance| field_instance.def_uid == biome_field_uid)
+            })
+            .map(|field_instance| match &field_instance.value {
+                FieldInstanceValue::Enum(value) => value.iter().cloned().collect(),
+                FieldInstanceValue::ArrayEnum(values) => values.iter().flatten().cloned().collect(),
+                _ => vec![],
+            })
+            .unwrap_or_default();
+
+        let rules = layer_definition
+            .auto_rule_groups
+            .iter()
+            .filter(|group| group.active)
+            .filter(|group| !group.is_optional || layer_asset.optional_rules.contains(&group.uid))
+            .filter(|group| {
+                let mut required = group.required_biome_values.iter();
+                match group.biome_requirement_mode {
+                    0 => required.all(|value| biome_values.contains(value)),
+                    _ => {
+                        group.required_biome_values.is_empty()
+                            || required.any(|value| biome_values.contains(value))
+                    }
+                }
+            })
+            .flat_map(|group| group.rules.iter())
+            .filter(|rule| rule.active)
+            .collect();
+
+        Self {
+            rules,
+            seed: layer_asset.seed,
+            grid_cell_size: layer_definition.grid_cell_size,
+            tile_pivot: layer_definition.tile_pivot,
+            tileset_definition,
+        }
+    }
+
+    fn radius(&self) -> i64 {
+        self.rules
+            .iter()
+            .map(|rule| rule.size / 2)
+            .max()
+            .unwrap_or_default()
+    }
+
+    // Every rule of the layer against a single cell. Rules are tried in order, until one with
+    // break_on_match matches.
+    fn evaluate_cell(&self, source: &IntGrid, cell: I64Vec2) -> Vec<(i64, Vec<TileInstance>)> {
+        let mut placed = vec![];
+
+        for rule in &self.rules {
+            if !self.in_modulo(rule, cell) {
+                continue;
+            }
+
+            let mut matched = false;
+            let mut tiles = vec![];
+
+            // flip bits: 1 for x, 2 for y
+            for flips in 0..4 {
+                let (flip_x, flip_y) = (flips & 1 == 1, flips & 2 == 2);
+
+                if (flip_x && !rule.flip_x) || (flip_y && !rule.flip_y) {
+                    continue;
+                }
+
+                if matched && rule.break_on_match {
+                    break;
+                }
+
+                if self.matches(rule, source, cell, flips) {
+                    tiles.extend(self.rule_tiles(rule, cell, flips));
+                    matched = true;
+                }
+            }
+
+            if !matched {
+                continue;
+            }
+
+            placed.push((rule.uid, tiles));
+
+            if rule.break_on_match {
+                break;
+            }
+        }
+
+        placed
+    }
+
+    fn in_modulo(&self, rule: &AutoLayerRule, cell: I64Vec2) -> bool {
+        let cell = cell - rule.offset;
+        let (modulo, checker) = (rule.modulo, rule.checker);
+
+        let x = match checker {
+            AutoLayerRuleChecker::Horizontal => cell.x + (cell.y / modulo.y) % 2,
+            _ => cell.x,
+        };
+
+        let y = match checker {
+            AutoLayerRuleChecker::Vertical => cell.y + (cell.x / modulo.x) % 2,
+            _ => cell.y,
+        };
+
+        x % modulo.x == 0 && y % modulo.y == 0
+    }
+
+    fn matches(&self, rule: &AutoLayerRule, source: &IntGrid, cell: I64Vec2, flips: i64) -> bool {
+        if rule.tile_rects_ids.is_empty() {
+            return false;
+        }
+
+        if rule.chance <= 0.0
+            || (rule.chance < 1.0
+                && rand_seed_coords(self.seed + rule.uid, cell, 100) as f64 >= rule.chance * 100.0)
+        {
+            return false;
+        }
+
+        let direction = I64Vec2::new(
+            if flips & 1 == 1 { -1 } else { 1 },
+            if flips & 2 == 2 { -1 } else { 1 },
+        );
+        let radius = rule.size / 2;
+
+        (0..rule.size * rule.size).all(|index| {
+            let pattern = rule
+                .pattern
+                .get(index as usize)
+                .copied()
+                .unwrap_or_default();
+
+            if pattern == 0 {
+                return true;
+            }
+
+            let offset = I64Vec2::new(index % rule.size, index / rule.size) - radius;
+
+            let Some(value) = source
+                .get(cell + direction * offset)
+                .or(rule.out_of_bounds_value)
+            else {
+                return false;
+            };
+
+            let expected = pattern > 0;
+
+            if pattern.abs() == ANYTHING {
+                (value != 0) == expected
+            } else if pattern.abs() > 999 {
+                let group_uid = pattern.abs() / 1000 - 1;
+                let group = source
+                    .definition(value)
+                    .map(|definition| definition.group_uid);
+                (group == Some(group_uid)) == expected
+            } else {
+                (value == pattern.abs()) == expected
+            }
+        })
+    }
+
+    fn rule_tiles(&self, rule: &AutoLayerRule, cell: I64Vec2, flips: i64) -> Vec<TileInstance> {
+        let tileset = self.tileset_definition;
+
+        let index = rand_seed_coords(
+            rule.uid + self.seed + flips,
+            cell,
+            rule.tile_rects_ids.len() as i64,
+        );
+        let tile_ids = &rule.tile_rects_ids[index as usize];
+
+        let sign = Vec2::new(
+            if flips & 1 == 1 { -1.0 } else { 1.0 },
+            if flips & 2 == 2 { -1.0 } else { 1.0 },
+        );
+
+        // Stamps are placed around the rule's pivot, within the bounds of the tiles
+        let (stamp_min, stamp_max) = tile_ids
+            .iter()
+            .map(|tile_id| tileset.tile_cell(*tile_id))
+            .fold((I64Vec2::MAX, I64Vec2::MIN), |(min, max), tile_cell| {
+                (min.min(tile_cell), max.max(tile_cell))
+            });
+
+        let random_offset = |(min, max): (i64, i64), seed: i64| {
+            if min == 0 && max == 0 {
+                0
+            } else {
+                rand_seed_coords(seed, cell, max - min + 1) + min
+            }
+        };
+
+        let offset = I64Vec2::new(
+            rule.tile_offset.x + random_offset(rule.tile_random_x, rule.uid + self.seed + flips),
+            rule.tile_offset.y
+                + random_offset(rule.tile_random_y, rule.uid + self.seed + flips + 1),
+        )
+        .as_vec2()
+            * sign;
+
+        tile_ids
+            .iter()
+            .map(|tile_id| {
+                let tile_cell = tileset.tile_cell(*tile_id);
+
+                let stamp_offset = match rule.tile_mode {
+                    AutoLayerRuleTileMode::Single => Vec2::ZERO,
+                    AutoLayerRuleTileMode::Stamp => {
+                        ((tile_cell - stamp_min).as_vec2()
+                            - rule.pivot * (stamp_max - stamp_min).as_vec2()
+                            + self.tile_pivot)
+                            * self.grid_cell_size as f32
+                    }
+                };
+
+                let stamp_offset = stamp_offset.trunc() * sign;
+
+                TileInstance {
+                    alpha: rule.alpha,
+                    flip_h: flips & 1 == 1,
+                    flip_v: flips & 2 == 2,
+                    location: cell * self.grid_cell_size
+                        + stamp_offset.as_i64vec2()
+                        + offset.as_i64vec2(),
+                    source: tileset.tile_source(*tile_id),
+                    tileset_id: *tile_id,
+                }
+            })
+            .collect()
+    }
+}
+
+// dn.M.randSeedCoords as the editor runs it: in JavaScript doubles, truncated to 32 bits by
+// each bitwise operator. The second multiplication loses precision, and has to lose it the
+// same way to pick the same tiles.
+fn rand_seed_coords(seed: i64, cell: I64Vec2, max: i64) -> i64 {
+    let to_int32 = |value: f64| value.rem_euclid(4294967296.0) as u32 as i32;
+
+    let hash = to_int32((seed + cell.x * 374761393 + cell.y * 668265263) as f64);
+    let hash = hash ^ (hash >> 13);
+    let hash = to_int32(hash as f64 * 1274126177.0);
+
+    ((hash ^ (hash >> 16)) as i64).rem_euclid(max.max(1))
+}
+
+// The tiles each rule placed, per cell, and the source values they were evaluated against, so
+// that IntGrid edits only re-evaluate the cells around the ones that changed.
+#[derive(Component, Debug, Default)]
+pub(crate) struct AutoLayerCache {
+    source_values: Vec<i64>,
+    // Rule uids, in the order their tiles are drawn
+    rule_order: Vec<i64>,
+    rule_tiles: HashMap<i64, BTreeMap<i64, Vec<TileInstance>>>,
+}
+
+impl AutoLayerCache {
+    pub(crate) fn new(rules: &AutoLayerRules, source: &IntGrid) -> Self {
+        let mut cache = Self::default();
+        cache.evaluate(rules, source, (0..source.values.len()).collect());
+        cache
+    }
+
+    // Returns the pixels covered by the tiles that were removed or placed, if any.
+    pub(crate) fn update(&mut self, rules: &AutoLayerRules, source: &IntGrid) -> Option<IRect> {
+        if self.source_values.len() != source.values.len() {
+            *self = Self::new(rules, source);
+            return Some(IRect::from_corners(
+                IVec2::ZERO,
+                (source.grid_size * source.grid_cell_size).as_ivec2(),
+            ));
+        }
+
+        let radius = rules.radius();
+
+        let cells = source
+            .values
+            .iter()
+            .zip(&self.source_values)
+            .enumerate()
+            .filter(|(_, (value, previous))| value != previous)
+            .flat_map(|(index, _)| {
+                let cell = grid_from_index(source, index);
+                (-radius..=radius)
+                    .flat_map(move |y| (-radius..=radius).map(move |x| cell + I64Vec2::new(x, y)))
+            })
+            .filter(|cell| cell.cmpge(I64Vec2::ZERO).all() && cell.cmplt(source.grid_size).all())
+            .map(|cell| (cell.y * source.grid_size.x + cell.x) as usize)
+            .collect::<HashSet<_>>();
+
+        self.evaluate(rules, source, cells.into_iter().collect())
+    }
+
+    // Drawn like the editor exports them: rules in reverse evaluation order, then by cell.
+    pub(crate) fn tiles(&self) -> Vec<TileInstance> {
+        self.iter().map(|(_, _, tile)| tile.clone()).collect()
+    }
+
+    // With the rule uid and coord id LDtk keeps with each auto-layer tile
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (i64, i64, &TileInstance)> {
+        self.rule_order
+            .iter()
+            .filter_map(|rule_uid| Some((*rule_uid, self.rule_tiles.get(rule_uid)?)))
+            .flat_map(|(rule_uid, cells)| {
+                cells.iter().flat_map(move |(coord_id, tiles)| {
+                    tiles.iter().map(move |tile| (rule_uid, *coord_id, tile))
+                })
+            })
+    }
+
+    fn evaluate(
+        &mut self,
+        rules: &AutoLayerRules,
+        source: &IntGrid,
+        indices: Vec<usize>,
+    ) -> Option<IRect> {
+        self.source_values.clone_from(&source.values);
+        self.rule_order = rules.rules.iter().rev().map(|rule| rule.uid).collect();
+
+        let mut dirty: Option<IRect> = None;
+        let mut mark = |tiles: &[TileInstance]| {
+            for tile in tiles {
+                let rect = tile_rect(rules.tileset_definition, tile);
+                dirty = Some(dirty.map_or(rect, |dirty| dirty.union(rect)));
+            }
+        };
+
+        for index in indices {
+            let cell = grid_from_index(source, index);
+            let coord_id = index as i64;
+
+            self.rule_tiles.values_mut().for_each(|cells| {
+                if let Some(tiles) = cells.remove(&coord_id) {
+                    mark(&tiles);
+                }
+            });
+
+            for (rule_uid, tiles) in rules.evaluate_cell(source, cell) {
+                mark(&tiles);
+                self.rule_tiles
+                    .entry(rule_uid)
+                    .or_default()
+                    .insert(coord_id, tiles);
+            }
+        }
+
+        dirty
+    }
+
+    // Recomputes the Tiles of every layer whose rules read from an IntGrid that changed:
+    // IntGrid layers with rules of their own, and the AutoLayer layers sourcing from them.
+    // IntGrids matching their asset are left alone, since the exported tiles are current.
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    pub(crate) fn auto_layer_system(
+        mut commands: Commands,
+        mut events: EventReader<LdtkAssetLoadEvent<LayerAsset>>,
+        project_commands: LdtkProjectCommands,
+        changed: Query<(Entity, Ref<IntGrid>, &Handle<LayerAsset>, &Parent), Changed<IntGrid>>,
+        layers: Query<&Handle<LayerAsset>>,
+        levels: Query<(&Handle<LevelAsset>, &Children)>,
+        mut caches: Query<&mut AutoLayerCache>,
+        layer_assets: Res<Assets<LayerAsset>>,
+        level_assets: Res<Assets<LevelAsset>>,
+    ) {
+        // A reload may change the rules too, so nothing cached can be trusted
+        let reloaded = events
+            .read()
+            .map(|LdtkAssetLoadEvent { entity, .. }| {
+                commands.entity(*entity).remove::<AutoLayerCache>();
+                *entity
+            })
+            .collect::<HashSet<_>>();
+
+        // A bad layer is logged and skipped, so that it can't hold back the others
+        for (source_entity, int_grid, source_handle, parent) in changed.iter() {
+            if int_grid.is_added() {
+                continue;
+            }
+
+            let Some(source_asset) = layer_assets.get(source_handle) else {
+                error!("{}", AutoLayerError::BadHandle);
+                continue;
+            };
+
+            let Some(project_asset) = project_commands.with_iid(&source_asset.project_iid) else {
+                error!("{}", AutoLayerError::BadProjectIid);
+                continue;
+            };
+
+            let Ok((level_handle, siblings)) = levels.get(parent.get()) else {
+                error!("{}", AutoLayerError::ParentNotLevel);
+                continue;
+            };
+
+            let Some(level_asset) = level_assets.get(level_handle) else {
+                error!("{}", AutoLayerError::BadHandle);
+                continue;
+            };
+
+            for entity in siblings.iter() {
+                let Ok(handle) = layers.get(*entity) else {
+                    continue;
+                };
+
+                let Some(layer_asset) = layer_assets.get(handle) else {
+                    error!("{}", AutoLayerError::BadHandle);
+                    continue;
+                };
+
+                let Some(layer_definition) =
+                    project_asset.layer_defs.get(&layer_asset.layer_def_uid)
+                else {
+                    error!("{}", AutoLayerError::BadLayerDefUid);
+                    continue;
+                };
+
+                let is_dependent = match layer_definition.layer_definition_type {
+                    LayerType::IntGrid => *entity == source_entity,
+                    LayerType::Autolayer => {
+                        layer_definition.auto_source_layer_def_uid
+                            == Some(source_asset.layer_def_uid)
+                    }
+                    _ => false,
+                };
+
+                if !is_dependent || layer_definition.auto_rule_groups.is_empty() {
+                    continue;
+                }
+
+                let Some(tileset_uid) = layer_asset
+                    .override_tileset_uid
+                    .or(layer_asset.tileset_def_uid)
+                else {
+                    continue;
+                };
+
+                let Some(tileset_definition) = project_asset.tileset_defs.get(&tileset_uid) else {
+                    error!("{}", AutoLayerError::BadTilesetUid);
+                    continue;
+                };
+
+                let rules = AutoLayerRules::new(
+                    layer_definition,
+                    layer_asset,
+                    &level_asset.field_instances,
+                    tileset_definition,
+                );
+
+                let tiles = match caches.get_mut(*entity) {
+                    Ok(mut cache) if !reloaded.contains(entity) => {
+                        let Some(dirty) = cache.update(&rules, &int_grid) else {
+                            continue;
+                        };
+                        let entity = *entity;
+                        commands.add(move |world: &mut World| {
+                            TilesDirtyRegions::add(world, entity, dirty);
+                        });
+                        cache.tiles()
+                    }
+                    _ if int_grid.values == source_asset.int_grid_csv => continue,
+                    _ => {
+                        let cache = AutoLayerCache::new(&rules, &int_grid);
+                        let tiles = cache.tiles();
+                        commands.entity(*entity).try_insert(cache);
+                        tiles
+                    }
+                };
+
+                if tiles.is_empty() {
+                    commands.entity(*entity).remove::<Tiles>();
+                } else {
+                    commands.entity(*entity).try_insert(Tiles { tiles });
+                }
+            }
+        }
+    }
+}
+
+fn grid_from_index(int_grid: &IntGrid, index: usize) -> I64Vec2 {
+    let index = index as i64;
+    (index % int_grid.grid_size.x, index / int_grid.grid_size.x).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defs::int_grid_value::IntGridValue;
+    use crate::ldtk;
+
+    struct Fixture {
+        layer_asset: LayerAsset,
+        layer_definition: LayerDefinition,
+        tileset_definition: TilesetDefinition,
+        int_grid: IntGrid,
+        exported: Vec<ldtk::TileInstance>,
+    }
+
+    fn fixture(project: &str, layer_identifier: &str) -> Fixture {
+        let project: ldtk::LdtkJson = serde_json::from_str(project).expect("valid project");
+
+        let level = project
+            .levels
+            .iter()
+            .chain(project.worlds.iter().flat_map(|world| world.levels.iter()))
+            .next()
+            .expect("a level");
+
+        let layer = level
+            .layer_instances
+            .as_ref()
+            .expect("embedded layers")
+            .iter()
+            .find(|layer| layer.identifier == layer_identifier)
+            .expect("the layer");
+
+        let layer_definition = project
+            .defs
+            .layers
+            .iter()
+            .find(|layer_definition| layer_definition.uid == layer.layer_def_uid)
+            .expect("a layer definition");
+
+        let tileset_definition = project
+            .defs
+            .tilesets
+            .iter()
+            .find(|tileset| Some(tileset.uid) == layer.tileset_def_uid)
+            .expect("a tileset");
+
+        let layer_definition = LayerDefinition::new(layer_definition).expect("valid definition");
+
+        let int_grid = IntGrid {
+            grid_size: (layer.c_wid, layer.c_hei).into(),
+            grid_cell_size: layer.grid_size,
+            values: layer.int_grid_csv.clone(),
+            int_grid_values: layer_definition.int_grid_values.clone(),
+            int_grid_values_groups: layer_definition.int_grid_values_groups.clone(),
+        };
+
+        Fixture {
+            layer_asset: LayerAsset::new(
+                layer,
+                0,
+                LayerType::IntGrid,
+                vec![],
+                vec![],
+                0.0,
+                level.iid.clone(),
+                project.iid.clone(),
+            )
+            .expect("valid layer"),
+            layer_definition,
+            tileset_definition: TilesetDefinition::new(tileset_definition),
+            int_grid,
+            exported: layer.auto_layer_tiles.clone(),
+        }
+    }
+
+    fn rules(fixture: &Fixture) -> AutoLayerRules<'_> {
+        AutoLayerRules::new(
+            &fixture.layer_definition,
+            &fixture.layer_asset,
+            &[],
+            &fixture.tileset_definition,
+        )
+    }
+
+    fn key(tile: &TileInstance) -> (I64Vec2, UVec2, i64, bool, bool) {
+        (
+            tile.location,
+            tile.source,
+            tile.tileset_id,
+            tile.flip_h,
+            tile.flip_v,
+        )
+    }
+
+    #[test]
+    fn matches_the_editor_export() {
+        for (project, layer_identifier) in [
+            (include_str!("../../assets/ldtk/top_down.ldtk"), "Ground"),
+            (include_str!("../../assets/ldtk/top_down.ldtk"), "Bridges"),
+            (include_str!("../../assets/ldtk/side_scroller.ldtk"), "Cave"),
+            (
+                include_str!("../../assets/ldtk/side_scroller.ldtk"),
+                "Backdrop",
+            ),
+        ] {
+            let fixture = fixture(project, layer_identifier);
+            let rules = rules(&fixture);
+            let tiles = AutoLayerCache::new(&rules, &fixture.int_grid).tiles();
+
+            let exported = fixture
+                .exported
+                .iter()
+                .map(TileInstance::new)
+                .map(|tile| key(&tile))
+                .collect::<Vec<_>>();
+
+            assert!(!exported.is_empty());
+            assert_eq!(tiles.iter().map(key).collect::<Vec<_>>(), exported);
+        }
+    }
+
+    #[test]
+    fn updates_match_a_full_evaluation() {
+        let mut fixture = fixture(include_str!("../../assets/ldtk/side_scroller.ldtk"), "Cave");
+        let mut cache = AutoLayerCache::new(&rules(&fixture), &fixture.int_grid);
+
+        let width = fixture.int_grid.grid_size.x as usize;
+        for index in [
+            0,
+            width + 1,
+            5 * width + 7,
+            fixture.int_grid.values.len() - 1,
+        ] {
+            fixture.int_grid.values[index] = 1 - fixture.int_grid.values[index].min(1);
+        }
+
+        assert!(cache.update(&rules(&fixture), &fixture.int_grid).is_some());
+        let updated = cache.tiles();
+
+        let rules = rules(&fixture);
+        let full = AutoLayerCache::new(&rules, &fixture.int_grid).tiles();
+
+        assert_eq!(
+            updated.iter().map(key).collect::<Vec<_>>(),
+            full.iter().map(key).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn pattern_groups_and_anything() {
+        let int_grid = IntGrid {
+            grid_size: (2, 1).into(),
+            grid_cell_size: 16,
+            values: vec![1, 0],
+            int_grid_values: vec![IntGridValue {
+                value: 1,
+                group_uid: 3,
+                ..default()
+            }],
+            int_grid_values_groups: vec![],
+        };
+
+        let matches = |pattern: i64, cell: I64Vec2| {
+            let rule = AutoLayerRule {
+                pattern: vec![pattern],
+                size: 1,
+                ..fixture_rule()
+            };
+            let tileset_definition = TilesetDefinition::default();
+            let rules = AutoLayerRules {
+                rules: vec![],
+                seed: 0,
+                grid_cell_size: 16,
+                tile_pivot: Vec2::ZERO,
+                tileset_definition: &tileset_definition,
+            };
+            rules.matches(&rule, &int_grid, cell, 0)
+        };
+
+        assert!(matches(ANYTHING, (0, 0).into()));
+        assert!(!matches(ANYTHING, (1, 0).into()));
+        assert!(matches(-ANYTHING, (1, 0).into()));
+        assert!(matches(4000, (0, 0).into()));
+        assert!(!matches(-4000, (0, 0).into()));
+        assert!(matches(-1, (1, 0).into()));
+    }
+
+    fn fixture_rule() -> AutoLayerRule {
+        AutoLayerRule {
+            active: true,
+            alpha: 1.0,
+            break_on_match: true,
+            chance: 1.0,
+            checker: AutoLayerRuleChecker::None,
+            flip_x: false,
+            flip_y: false,
+            out_of_bounds_value: None,
+            pattern: vec![],
+            pivot: Vec2::ZERO,
+            size: 1,
+            tile_mode: AutoLayerRuleTileMode::Single,
+            tile_offset: I64Vec2::ZERO,
+            tile_random_x: (0, 0),
+            tile_random_y: (0, 0),
+            tile_rects_ids: vec![vec![0]],
+            uid: 0,
+            modulo: I64Vec2::ONE,
+            offset: I64Vec2::ZERO,
+        }
+    }
+}
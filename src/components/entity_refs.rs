@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use crate::assets::entity::EntityAsset;
+use crate::components::iid::Iid;
+use crate::components::iid::IidMap;
+use crate::exports::field_instance::FieldInstance;
+use crate::exports::field_instance::FieldInstanceValue;
+use crate::exports::reference_to_an_entity_instance::ReferenceToAnEntityInstance;
+
+// One EntityRef field value. `entity` is the spawned target, or None while the target isn't
+// spawned (for instance, when its level isn't selected).
+#[derive(Clone, Debug, Reflect)]
+pub struct EntityRef {
+    pub field_identifier: String,
+    pub reference: ReferenceToAnEntityInstance,
+    pub entity: Option<Entity>,
+}
+
+// From the EntityRef and Array<EntityRef> fields of an LDtk entity, in field order. Targets are
+// resolved across levels and worlds, and kept current as entities are spawned and despawned.
+#[derive(Clone, Component, Debug, Default, Reflect)]
+pub struct EntityRefs {
+    pub refs: Vec<EntityRef>,
+}
+
+impl EntityRefs {
+    pub(crate) fn new(field_instances: &[FieldInstance]) -> Self {
+        let refs = field_instances
+            .iter()
+            .flat_map(|field_instance| {
+                let references = match &field_instance.value {
+                    FieldInstanceValue::EntityRef(reference) => reference.iter().cloned().collect(),
+                    FieldInstanceValue::ArrayEntityRef(references) => {
+                        references.iter().flatten().cloned().collect()
+                    }
+                    _ => vec![],
+                };
+
+                references.into_iter().map(|reference| EntityRef {
+                    field_identifier: field_instance.identifier.clone(),
+                    reference,
+                    entity: None,
+                })
+            })
+            .collect();
+
+        Self { refs }
+    }
+
+    // The first resolved target of the given field
+    pub fn get(&self, field_identifier: &str) -> Option<Entity> {
+        self.get_all(field_identifier).next()
+    }
+
+    // Every resolved target of the given field
+    pub fn get_all<'a>(&'a self, field_identifier: &'a str) -> impl Iterator<Item = Entity> + 'a {
+        self.refs
+            .iter()
+            .filter(move |entity_ref| entity_ref.field_identifier == field_identifier)
+            .filter_map(|entity_ref| entity_ref.entity)
+    }
+
+    // Every resolved target, from any field
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.refs.iter().filter_map(|entity_ref| entity_ref.entity)
+    }
+
+    // Re-resolves every reference whenever an LDtk entity gets its Iid or loses it. Runs after
+    // IidMap::iid_map_system, so targets despawned this frame are already gone from the map.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn entity_refs_system(
+        mut query: Query<&mut EntityRefs>,
+        changed_refs: Query<(), Changed<EntityRefs>>,
+        added_entities: Query<(), (Added<Iid>, With<Handle<EntityAsset>>)>,
+        mut removed_iids: RemovedComponents<Iid>,
+        iid_map: Res<IidMap>,
+    ) {
+        let removed = removed_iids.read().count() > 0;
+
+        if !removed && added_entities.is_empty() && changed_refs.is_empty() {
+            return;
+        }
+
+        for mut entity_refs in query.iter_mut() {
+            let resolved = entity_refs
+                .refs
+                .iter()
+                .map(|entity_ref| iid_map.entity(&entity_ref.reference.entity_iid))
+                .collect::<Vec<_>>();
+
+            // Avoid triggering change detection when nothing moved
+            if entity_refs
+                .refs
+                .iter()
+                .zip(resolved.iter())
+                .any(|(entity_ref, entity)| entity_ref.entity != *entity)
+            {
+                entity_refs
+                    .refs
+                    .iter_mut()
+                    .zip(resolved)
+                    .for_each(|(entity_ref, entity)| entity_ref.entity = entity);
+            }
+        }
+    }
+}
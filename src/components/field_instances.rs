@@ -1,8 +1,151 @@
+use bevy::math::I64Vec2;
 use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
 
+use crate::components::tileset_rectangle::TilesetRectangle;
+use crate::exports::field_instance::field_instances_to_json;
 use crate::exports::field_instance::FieldInstance;
+use crate::exports::field_instance::FieldInstanceValueAsError;
+use crate::exports::reference_to_an_entity_instance::ReferenceToAnEntityInstance;
+
+#[derive(Debug, Error)]
+pub enum FieldInstancesError {
+    #[error(transparent)]
+    FieldInstanceValueAsError(#[from] FieldInstanceValueAsError),
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("No field instance with identifier! {0}")]
+    UnknownIdentifier(String),
+}
 
 #[derive(Clone, Component, Debug, Reflect)]
 pub struct FieldInstances {
     pub field_instances: Vec<FieldInstance>,
 }
+
+impl FieldInstances {
+    // Field identifiers are used as keys, so use #[serde(rename = "...")] on struct fields
+    // whose names differ from the LDtk identifier.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, FieldInstancesError> {
+        Ok(serde_json::from_value(field_instances_to_json(
+            &self.field_instances,
+        )?)?)
+    }
+
+    pub fn get(&self, identifier: &str) -> Result<&FieldInstance, FieldInstancesError> {
+        self.field_instances
+            .iter()
+            .find(|field_instance| field_instance.identifier == identifier)
+            .ok_or(FieldInstancesError::UnknownIdentifier(
+                identifier.to_owned(),
+            ))
+    }
+
+    pub fn get_int(&self, identifier: &str) -> Result<Option<i64>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_int()?)
+    }
+
+    pub fn get_float(&self, identifier: &str) -> Result<Option<f64>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_float()?)
+    }
+
+    pub fn get_string(&self, identifier: &str) -> Result<Option<&str>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_string()?)
+    }
+
+    pub fn get_bool(&self, identifier: &str) -> Result<bool, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_bool()?)
+    }
+
+    pub fn get_color(&self, identifier: &str) -> Result<Option<Color>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_color()?)
+    }
+
+    pub fn get_file_path(&self, identifier: &str) -> Result<Option<&str>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_file_path()?)
+    }
+
+    pub fn get_enum(&self, identifier: &str) -> Result<Option<&str>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_enum()?)
+    }
+
+    pub fn get_tile(
+        &self,
+        identifier: &str,
+    ) -> Result<Option<&TilesetRectangle>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_tile()?)
+    }
+
+    pub fn get_entity_ref(
+        &self,
+        identifier: &str,
+    ) -> Result<Option<&ReferenceToAnEntityInstance>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_entity_ref()?)
+    }
+
+    pub fn get_point(&self, identifier: &str) -> Result<Option<I64Vec2>, FieldInstancesError> {
+        Ok(self.get(identifier)?.as_point()?)
+    }
+
+    pub fn get_array_int(&self, identifier: &str) -> Result<&[Option<i64>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_int()?)
+    }
+
+    pub fn get_array_float(&self, identifier: &str) -> Result<&[Option<f64>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_float()?)
+    }
+
+    pub fn get_array_string(
+        &self,
+        identifier: &str,
+    ) -> Result<&[Option<String>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_string()?)
+    }
+
+    pub fn get_array_bool(&self, identifier: &str) -> Result<&[bool], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_bool()?)
+    }
+
+    pub fn get_array_color(
+        &self,
+        identifier: &str,
+    ) -> Result<&[Option<Color>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_color()?)
+    }
+
+    pub fn get_array_file_path(
+        &self,
+        identifier: &str,
+    ) -> Result<&[Option<String>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_file_path()?)
+    }
+
+    pub fn get_array_enum(
+        &self,
+        identifier: &str,
+    ) -> Result<&[Option<String>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_enum()?)
+    }
+
+    pub fn get_array_tile(
+        &self,
+        identifier: &str,
+    ) -> Result<&[Option<TilesetRectangle>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_tile()?)
+    }
+
+    pub fn get_array_entity_ref(
+        &self,
+        identifier: &str,
+    ) -> Result<&[Option<ReferenceToAnEntityInstance>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_entity_ref()?)
+    }
+
+    pub fn get_array_point(
+        &self,
+        identifier: &str,
+    ) -> Result<&[Option<I64Vec2>], FieldInstancesError> {
+        Ok(self.get(identifier)?.as_array_point()?)
+    }
+}
@@ -1,4 +1,108 @@
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::borrow::Borrow;
+use std::fmt::Display;
+use std::sync::Arc;
 
-#[derive(Component, Debug, Reflect)]
-pub struct Iid(pub String);
+// Shared, so clones are cheap. Hashes like a str, so maps keyed by Iid can be queried with &str.
+#[derive(Clone, Component, Debug, Hash, PartialEq, Eq, Reflect)]
+#[reflect_value(Debug, Hash, PartialEq)]
+pub struct Iid(Arc<str>);
+
+impl Iid {
+    pub fn new(iid: &str) -> Self {
+        Self(iid.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Iid {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Iid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<&str> for Iid {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for Iid {
+    fn from(value: String) -> Self {
+        Self(value.into())
+    }
+}
+
+// Every spawned project, world, level, layer and entity by iid. Handles are weak, so the map
+// never keeps an asset loaded.
+#[derive(Debug, Default, Resource)]
+pub struct IidMap {
+    entities: HashMap<Iid, Entity>,
+    handles: HashMap<Iid, UntypedHandle>,
+    iids: HashMap<Entity, Iid>,
+}
+
+impl IidMap {
+    pub fn entity(&self, iid: &str) -> Option<Entity> {
+        self.entities.get(iid).copied()
+    }
+
+    pub fn handle<A: Asset>(&self, iid: &str) -> Option<Handle<A>> {
+        self.handles
+            .get(iid)
+            .and_then(|handle| handle.clone().try_typed().ok())
+    }
+
+    pub fn untyped_handle(&self, iid: &str) -> Option<&UntypedHandle> {
+        self.handles.get(iid)
+    }
+
+    pub fn iid(&self, entity: Entity) -> Option<&Iid> {
+        self.iids.get(&entity)
+    }
+
+    pub fn contains(&self, iid: &str) -> bool {
+        self.entities.contains_key(iid)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Iid, Entity)> {
+        self.entities.iter().map(|(iid, entity)| (iid, *entity))
+    }
+
+    pub(crate) fn insert<A: Asset>(&mut self, iid: Iid, entity: Entity, handle: &Handle<A>) {
+        if let Some(previous) = self.entities.insert(iid.clone(), entity) {
+            if previous != entity {
+                self.iids.remove(&previous);
+            }
+        }
+        self.handles
+            .insert(iid.clone(), handle.clone_weak().untyped());
+        self.iids.insert(entity, iid);
+    }
+
+    pub(crate) fn iid_map_system(
+        mut iid_map: ResMut<IidMap>,
+        mut removed_iids: RemovedComponents<Iid>,
+    ) {
+        for entity in removed_iids.read() {
+            let Some(iid) = iid_map.iids.remove(&entity) else {
+                continue;
+            };
+
+            if iid_map.entities.get(&iid) == Some(&entity) {
+                iid_map.entities.remove(&iid);
+                iid_map.handles.remove(&iid);
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+
+use crate::defs::int_grid_value::IntGridValue;
+use crate::defs::int_grid_value_group::IntGridValueGroup;
+
+// Grid coordinates follow LDtk: (0, 0) is the top left cell, and y grows downward.
+// Local coordinates are in the layer's Bevy space, where the grid spans (0, 0) to (w, -h).
+#[derive(Clone, Component, Debug, Reflect)]
+pub struct IntGrid {
+    pub grid_size: I64Vec2,
+    pub grid_cell_size: i64,
+    pub values: Vec<i64>,
+    pub int_grid_values: Vec<IntGridValue>,
+    pub int_grid_values_groups: Vec<IntGridValueGroup>,
+}
+
+impl IntGrid {
+    pub fn get(&self, grid: I64Vec2) -> Option<i64> {
+        self.index(grid).map(|index| self.values[index])
+    }
+
+    // Returns false if the cell is outside of the grid
+    pub fn set(&mut self, grid: I64Vec2, value: i64) -> bool {
+        let Some(index) = self.index(grid) else {
+            return false;
+        };
+        self.values[index] = value;
+        true
+    }
+
+    pub fn get_at_local(&self, local: Vec2) -> Option<i64> {
+        self.get(self.grid_from_local(local)?)
+    }
+
+    pub fn get_at_world(&self, global_transform: &GlobalTransform, world: Vec2) -> Option<i64> {
+        self.get(self.grid_from_world(global_transform, world)?)
+    }
+
+    pub fn grid_from_local(&self, local: Vec2) -> Option<I64Vec2> {
+        let grid = (Vec2::new(local.x, -local.y) / self.grid_cell_size as f32)
+            .floor()
+            .as_i64vec2();
+        self.index(grid).map(|_| grid)
+    }
+
+    pub fn grid_from_world(
+        &self,
+        global_transform: &GlobalTransform,
+        world: Vec2,
+    ) -> Option<I64Vec2> {
+        let local = global_transform
+            .affine()
+            .inverse()
+            .transform_point3(world.extend(0.0));
+        self.grid_from_local(local.truncate())
+    }
+
+    // The center of the given cell, in local coordinates
+    pub fn local_from_grid(&self, grid: I64Vec2) -> Vec2 {
+        let center = (grid.as_vec2() + 0.5) * self.grid_cell_size as f32;
+        Vec2::new(center.x, -center.y)
+    }
+
+    pub fn definition(&self, value: i64) -> Option<&IntGridValue> {
+        self.int_grid_values
+            .iter()
+            .find(|int_grid_value| int_grid_value.value == value)
+    }
+
+    pub fn definition_at(&self, grid: I64Vec2) -> Option<&IntGridValue> {
+        self.definition(self.get(grid)?)
+    }
+
+    pub fn group(&self, value: i64) -> Option<&IntGridValueGroup> {
+        let group_uid = self.definition(value)?.group_uid;
+        self.int_grid_values_groups
+            .iter()
+            .find(|group| group.uid == group_uid)
+    }
+
+    // Every non-empty cell
+    pub fn iter(&self) -> impl Iterator<Item = (I64Vec2, i64)> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(index, value)| (self.grid_from_index(index), *value))
+    }
+
+    pub fn iter_value(&self, value: i64) -> impl Iterator<Item = I64Vec2> + '_ {
+        self.iter()
+            .filter(move |(_, cell_value)| *cell_value == value)
+            .map(|(grid, _)| grid)
+    }
+
+    pub fn iter_group(&self, group_uid: i64) -> impl Iterator<Item = (I64Vec2, i64)> + '_ {
+        self.iter().filter(move |(_, value)| {
+            self.definition(*value)
+                .is_some_and(|definition| definition.group_uid == group_uid)
+        })
+    }
+
+    fn index(&self, grid: I64Vec2) -> Option<usize> {
+        (grid.cmpge(I64Vec2::ZERO).all() && grid.cmplt(self.grid_size).all())
+            .then(|| (grid.y * self.grid_size.x + grid.x) as usize)
+            .filter(|index| *index < self.values.len())
+    }
+
+    fn grid_from_index(&self, index: usize) -> I64Vec2 {
+        let index = index as i64;
+        (index % self.grid_size.x, index / self.grid_size.x).into()
+    }
+}
@@ -0,0 +1,460 @@
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use thiserror::Error;
+
+use crate::assets::layer::LayerAsset;
+use crate::components::int_grid::IntGrid;
+
+#[derive(Debug, Error)]
+pub enum IntGridCollidersError {
+    #[error("Bad handle?")]
+    BadHandle,
+}
+
+#[derive(Clone, Debug, Reflect)]
+pub enum IntGridColliderFilter {
+    // Any non-empty cell
+    NonEmpty,
+    Values(Vec<i64>),
+    // IntGridValueGroup identifier
+    Group(String),
+}
+
+#[derive(Clone, Copy, Debug, Reflect)]
+pub enum IntGridColliderShapes {
+    Rectangles,
+    Outlines,
+    Both,
+}
+
+#[derive(Clone, Debug, Reflect)]
+pub struct IntGridColliderRule {
+    pub layer_identifier: String,
+    pub filter: IntGridColliderFilter,
+    pub shapes: IntGridColliderShapes,
+}
+
+#[derive(Debug, Default, Reflect, Resource)]
+pub struct IntGridColliderRules {
+    pub rules: Vec<IntGridColliderRule>,
+}
+
+// One entry per rule matching the layer, in registration order.
+#[derive(Clone, Component, Debug, Default, Reflect)]
+pub struct IntGridColliders {
+    pub rules: Vec<IntGridRuleColliders>,
+}
+
+// Shapes are in the layer's local coordinates. Outlines are closed polygons without the
+// closing point repeated; outer boundaries and holes wind in opposite directions.
+#[derive(Clone, Debug, Reflect)]
+pub struct IntGridRuleColliders {
+    pub filter: IntGridColliderFilter,
+    pub rectangles: Vec<Rect>,
+    pub outlines: Vec<Vec<Vec2>>,
+}
+
+impl IntGridRuleColliders {
+    pub(crate) fn new(
+        int_grid: &IntGrid,
+        filter: &IntGridColliderFilter,
+        shapes: IntGridColliderShapes,
+    ) -> Self {
+        let cells = int_grid
+            .values
+            .iter()
+            .map(|value| filter.matches(int_grid, *value))
+            .collect::<Vec<_>>();
+
+        let to_local = |vertex: I64Vec2| {
+            let vertex = vertex.as_vec2() * int_grid.grid_cell_size as f32;
+            Vec2::new(vertex.x, -vertex.y)
+        };
+
+        let rectangles = match shapes {
+            IntGridColliderShapes::Rectangles | IntGridColliderShapes::Both => {
+                merge_rectangles(int_grid.grid_size, &cells)
+                    .into_iter()
+                    .map(|(min, max)| Rect::from_corners(to_local(min), to_local(max)))
+                    .collect()
+            }
+            IntGridColliderShapes::Outlines => vec![],
+        };
+
+        let outlines = match shapes {
+            IntGridColliderShapes::Outlines | IntGridColliderShapes::Both => {
+                trace_outlines(int_grid.grid_size, &cells)
+                    .into_iter()
+                    .map(|outline| outline.into_iter().map(to_local).collect())
+                    .collect()
+            }
+            IntGridColliderShapes::Rectangles => vec![],
+        };
+
+        Self {
+            filter: filter.clone(),
+            rectangles,
+            outlines,
+        }
+    }
+}
+
+impl IntGridColliders {
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn int_grid_colliders_system(
+        mut commands: Commands,
+        query: Query<(
+            Entity,
+            &Handle<LayerAsset>,
+            Ref<IntGrid>,
+            Has<IntGridColliders>,
+        )>,
+        mut removed_int_grids: RemovedComponents<IntGrid>,
+        rules: Res<IntGridColliderRules>,
+        layer_assets: Res<Assets<LayerAsset>>,
+    ) -> Result<(), IntGridCollidersError> {
+        // Changed rules can add or remove shapes on any layer
+        let changed = query
+            .iter()
+            .filter(|(_, _, int_grid, _)| rules.is_changed() || int_grid.is_changed());
+
+        for (entity, handle, int_grid, has_colliders) in changed {
+            let layer_asset = layer_assets
+                .get(handle)
+                .ok_or(IntGridCollidersError::BadHandle)?;
+
+            let mut matching = rules
+                .rules
+                .iter()
+                .filter(|rule| rule.layer_identifier == layer_asset.identifier)
+                .peekable();
+
+            if matching.peek().is_none() {
+                if has_colliders {
+                    commands.entity(entity).remove::<IntGridColliders>();
+                }
+                continue;
+            }
+
+            let colliders = IntGridColliders {
+                rules: matching
+                    .map(|rule| IntGridRuleColliders::new(&int_grid, &rule.filter, rule.shapes))
+                    .collect(),
+            };
+
+            commands.entity(entity).try_insert(colliders);
+        }
+
+        removed_int_grids.read().for_each(|entity| {
+            if let Some(mut entity_commands) = commands.get_entity(entity) {
+                entity_commands.remove::<IntGridColliders>();
+            }
+        });
+
+        Ok(())
+    }
+}
+
+impl IntGridColliderFilter {
+    fn matches(&self, int_grid: &IntGrid, value: i64) -> bool {
+        if value == 0 {
+            return false;
+        }
+
+        match self {
+            IntGridColliderFilter::NonEmpty => true,
+            IntGridColliderFilter::Values(values) => values.contains(&value),
+            IntGridColliderFilter::Group(identifier) => int_grid
+                .group(value)
+                .is_some_and(|group| group.identifier.as_ref() == Some(identifier)),
+        }
+    }
+}
+
+// Greedy meshing: grow each unvisited cell right as far as possible, then down while the whole
+// span below is solid. Returns (min, max) pairs in grid vertex coordinates.
+fn merge_rectangles(grid_size: I64Vec2, cells: &[bool]) -> Vec<(I64Vec2, I64Vec2)> {
+    let (width, height) = (grid_size.x as usize, grid_size.y as usize);
+    let mut visited = vec![false; cells.len()];
+    let mut rectangles = vec![];
+
+    let solid = |visited: &[bool], x: usize, y: usize| {
+        let index = y * width + x;
+        cells[index] && !visited[index]
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            if !solid(&visited, x, y) {
+                continue;
+            }
+
+            let mut right = x + 1;
+            while right < width && solid(&visited, right, y) {
+                right += 1;
+            }
+
+            let mut bottom = y + 1;
+            while bottom < height && (x..right).all(|column| solid(&visited, column, bottom)) {
+                bottom += 1;
+            }
+
+            for row in y..bottom {
+                for column in x..right {
+                    visited[row * width + column] = true;
+                }
+            }
+
+            rectangles.push((
+                I64Vec2::new(x as i64, y as i64),
+                I64Vec2::new(right as i64, bottom as i64),
+            ));
+        }
+    }
+
+    rectangles
+}
+
+// Walks the boundary edges of solid cells, keeping solid cells on the right hand side (with y
+// growing downward). Where two regions only touch at a corner, the walk turns right, so each
+// region gets its own outline. Collinear vertices are dropped.
+fn trace_outlines(grid_size: I64Vec2, cells: &[bool]) -> Vec<Vec<I64Vec2>> {
+    let solid = |cell: I64Vec2| {
+        cell.cmpge(I64Vec2::ZERO).all()
+            && cell.cmplt(grid_size).all()
+            && cells[(cell.y * grid_size.x + cell.x) as usize]
+    };
+
+    let mut edges: HashMap<I64Vec2, Vec<I64Vec2>> = HashMap::default();
+    let mut edge_count = 0;
+
+    for y in 0..grid_size.y {
+        for x in 0..grid_size.x {
+            let cell = I64Vec2::new(x, y);
+
+            if !solid(cell) {
+                continue;
+            }
+
+            let corners = [
+                cell,
+                cell + I64Vec2::X,
+                cell + I64Vec2::ONE,
+                cell + I64Vec2::Y,
+            ];
+
+            // top, right, bottom, left
+            let neighbours = [
+                cell - I64Vec2::Y,
+                cell + I64Vec2::X,
+                cell + I64Vec2::Y,
+                cell - I64Vec2::X,
+            ];
+
+            for side in 0..4 {
+                if !solid(neighbours[side]) {
+                    edges
+                        .entry(corners[side])
+                        .or_default()
+                        .push(corners[(side + 1) % 4]);
+                    edge_count += 1;
+                }
+            }
+        }
+    }
+
+    let mut starts = edges.keys().copied().collect::<Vec<_>>();
+    starts.sort_by_key(|vertex| (vertex.y, vertex.x));
+
+    let mut outlines = vec![];
+    let mut walked = 0;
+
+    for start in starts {
+        while let Some(first) = edges.get_mut(&start).and_then(|outgoing| outgoing.pop()) {
+            let mut outline = vec![start];
+            let mut previous = start;
+            let mut current = first;
+            walked += 1;
+
+            while current != start && walked <= edge_count {
+                outline.push(current);
+
+                let direction = current - previous;
+                let preferred = [
+                    I64Vec2::new(-direction.y, direction.x),
+                    direction,
+                    I64Vec2::new(direction.y, -direction.x),
+                ];
+
+                let Some(outgoing) = edges.get_mut(&current) else {
+                    break;
+                };
+
+                let Some(index) = preferred
+                    .iter()
+                    .find_map(|turn| outgoing.iter().position(|next| *next - current == *turn))
+                else {
+                    break;
+                };
+
+                previous = current;
+                current = outgoing.swap_remove(index);
+                walked += 1;
+            }
+
+            outlines.push(remove_collinear(outline));
+        }
+    }
+
+    outlines
+}
+
+fn remove_collinear(outline: Vec<I64Vec2>) -> Vec<I64Vec2> {
+    let len = outline.len();
+    (0..len)
+        .filter(|index| {
+            let previous = outline[(index + len - 1) % len];
+            let current = outline[*index];
+            let next = outline[(index + 1) % len];
+            (current - previous).perp_dot(next - current) != 0
+        })
+        .map(|index| outline[index])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ldtk;
+
+    fn side_scroller_cave() -> (I64Vec2, Vec<bool>) {
+        let project: ldtk::LdtkJson =
+            serde_json::from_str(include_str!("../../assets/ldtk/side_scroller.ldtk"))
+                .expect("valid project");
+
+        let layer = project.worlds[0].levels[0]
+            .layer_instances
+            .as_ref()
+            .expect("embedded layers")
+            .iter()
+            .find(|layer| layer.identifier == "Cave")
+            .expect("Cave layer")
+            .clone();
+
+        (
+            (layer.c_wid, layer.c_hei).into(),
+            layer.int_grid_csv.iter().map(|value| *value == 1).collect(),
+        )
+    }
+
+    #[test]
+    fn rectangles_cover_every_solid_cell_once() {
+        let (grid_size, cells) = side_scroller_cave();
+        let rectangles = merge_rectangles(grid_size, &cells);
+
+        let mut coverage = vec![0; cells.len()];
+        for (min, max) in &rectangles {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    coverage[(y * grid_size.x + x) as usize] += 1;
+                }
+            }
+        }
+
+        for (solid, covered) in cells.iter().zip(coverage) {
+            assert_eq!(covered, *solid as i32);
+        }
+
+        assert!(rectangles.len() < cells.iter().filter(|solid| **solid).count());
+    }
+
+    #[test]
+    fn outlines_are_closed_and_axis_aligned() {
+        let (grid_size, cells) = side_scroller_cave();
+        let outlines = trace_outlines(grid_size, &cells);
+
+        assert!(!outlines.is_empty());
+
+        for outline in &outlines {
+            assert!(outline.len() >= 4);
+            for (index, vertex) in outline.iter().enumerate() {
+                let next = outline[(index + 1) % outline.len()];
+                assert!(vertex.x == next.x || vertex.y == next.y);
+            }
+        }
+    }
+
+    #[test]
+    fn outlines_span_every_boundary_edge() {
+        let (grid_size, cells) = side_scroller_cave();
+        let outlines = trace_outlines(grid_size, &cells);
+
+        let solid = |x: i64, y: i64| {
+            x >= 0
+                && y >= 0
+                && x < grid_size.x
+                && y < grid_size.y
+                && cells[(y * grid_size.x + x) as usize]
+        };
+
+        let mut boundary_edges = 0;
+        for y in 0..grid_size.y {
+            for x in 0..grid_size.x {
+                if solid(x, y) {
+                    boundary_edges += [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                        .iter()
+                        .filter(|(dx, dy)| !solid(x + dx, y + dy))
+                        .count() as i64;
+                }
+            }
+        }
+
+        let perimeter: i64 = outlines
+            .iter()
+            .map(|outline| {
+                (0..outline.len())
+                    .map(|index| {
+                        let edge = outline[(index + 1) % outline.len()] - outline[index];
+                        edge.x.abs() + edge.y.abs()
+                    })
+                    .sum::<i64>()
+            })
+            .sum();
+
+        assert_eq!(perimeter, boundary_edges);
+    }
+
+    #[test]
+    fn diagonal_cells_get_separate_outlines() {
+        let outlines = trace_outlines((2, 2).into(), &[true, false, false, true]);
+
+        assert_eq!(outlines.len(), 2);
+        assert!(outlines.iter().all(|outline| outline.len() == 4));
+    }
+
+    #[test]
+    fn rules_keep_their_own_shapes() {
+        let int_grid = IntGrid {
+            grid_size: (2, 1).into(),
+            grid_cell_size: 8,
+            values: vec![1, 2],
+            int_grid_values: vec![],
+            int_grid_values_groups: vec![],
+        };
+
+        let colliders = [1, 2].map(|value| {
+            let filter = IntGridColliderFilter::Values(vec![value]);
+            IntGridRuleColliders::new(&int_grid, &filter, IntGridColliderShapes::Rectangles)
+        });
+
+        assert_eq!(
+            colliders[0].rectangles,
+            vec![Rect::new(0.0, 0.0, 8.0, -8.0)]
+        );
+        assert_eq!(
+            colliders[1].rectangles,
+            vec![Rect::new(8.0, 0.0, 16.0, -8.0)]
+        );
+    }
+}
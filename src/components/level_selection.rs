@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::assets::level::LevelAsset;
+use crate::assets::level_asset_loader::LevelAssetLoaderSettings;
+use crate::assets::world::WorldAsset;
+use crate::components::iid::Iid;
+
+#[derive(Debug, Error)]
+pub enum LevelSelectionError {
+    #[error("Bad handle?")]
+    BadHandle,
+}
+
+// Controls which levels of each world are spawned. Levels leaving the selection are despawned
+// along with their layers and entities.
+#[derive(Clone, Debug, Default, Reflect, Resource)]
+pub enum LevelSelection {
+    #[default]
+    All,
+    Identifiers(Vec<String>),
+    Iids(Vec<String>),
+    // Levels whose bounds are within this many pixels of any LevelSelectionAnchor
+    Near(f32),
+}
+
+// Add to the camera or player that LevelSelection::Near measures from.
+#[derive(Clone, Component, Debug, Default, Reflect)]
+pub struct LevelSelectionAnchor;
+
+impl LevelSelection {
+    fn selects(&self, level_asset: &LevelAsset, world_location: Vec2, anchors: &[Vec2]) -> bool {
+        match self {
+            LevelSelection::All => true,
+            LevelSelection::Identifiers(identifiers) => {
+                identifiers.contains(&level_asset.identifier)
+            }
+            LevelSelection::Iids(iids) => iids.contains(&level_asset.iid),
+            LevelSelection::Near(distance) => {
+                let bounds = level_asset.bounds();
+                let bounds =
+                    Rect::from_corners(bounds.min + world_location, bounds.max + world_location);
+
+                anchors.iter().any(|anchor| {
+                    (*anchor - anchor.clamp(bounds.min, bounds.max)).length() <= *distance
+                })
+            }
+        }
+    }
+
+    // Takes the place of the world's LdtkAssetChildLoader, spawning and despawning levels as the
+    // selection, the anchors, or the world asset change. External levels are spawned with their
+    // .ldtkl asset, which is only loaded once selected and dropped again once deselected.
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    pub(crate) fn level_selection_system(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        selection: Res<LevelSelection>,
+        worlds: Query<
+            (
+                Entity,
+                &Handle<WorldAsset>,
+                &GlobalTransform,
+                Option<&Children>,
+            ),
+            With<Iid>,
+        >,
+        levels: Query<&Handle<LevelAsset>>,
+        anchors: Query<&GlobalTransform, With<LevelSelectionAnchor>>,
+        world_assets: Res<Assets<WorldAsset>>,
+        level_assets: Res<Assets<LevelAsset>>,
+    ) -> Result<(), LevelSelectionError> {
+        let anchors = anchors
+            .iter()
+            .map(|anchor| anchor.translation().truncate())
+            .collect::<Vec<_>>();
+
+        for (entity, handle, global_transform, children) in worlds.iter() {
+            let world_asset = world_assets
+                .get(handle)
+                .ok_or(LevelSelectionError::BadHandle)?;
+
+            let world_location = global_transform.translation().truncate();
+
+            let spawned = children
+                .iter()
+                .flat_map(|children| children.iter())
+                .flat_map(|child| {
+                    levels
+                        .get(*child)
+                        .map(|level_handle| (*child, level_handle))
+                })
+                .collect::<Vec<_>>();
+
+            let selected = world_asset
+                .level_handles
+                .iter()
+                .map(|level_handle| {
+                    let level_asset = level_assets
+                        .get(level_handle)
+                        .ok_or(LevelSelectionError::BadHandle)?;
+                    let selected = selection.selects(level_asset, world_location, &anchors);
+                    let level_handle = match (selected, &level_asset.external_level) {
+                        // A spawned external level already holds the handle of its .ldtkl file,
+                        // so the asset server is only asked until it has loaded
+                        (true, Some((path, settings))) => spawned
+                            .iter()
+                            .map(|(_, spawned_handle)| *spawned_handle)
+                            .find(|spawned_handle| {
+                                level_assets
+                                    .get(*spawned_handle)
+                                    .is_some_and(|spawned| spawned.iid == level_asset.iid)
+                            })
+                            .cloned()
+                            .or_else(|| asset_server.get_handle(path))
+                            .unwrap_or_else(|| {
+                                let settings = settings.clone();
+                                asset_server.load_with_settings(
+                                    path.clone(),
+                                    move |loader_settings: &mut LevelAssetLoaderSettings| {
+                                        *loader_settings = settings.clone();
+                                    },
+                                )
+                            }),
+                        _ => level_handle.clone(),
+                    };
+                    Ok((level_handle, selected))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            selected
+                .iter()
+                .filter(|(level_handle, selected)| {
+                    *selected
+                        && !spawned
+                            .iter()
+                            .any(|(_, spawned_handle)| spawned_handle.id() == level_handle.id())
+                })
+                .for_each(|(level_handle, _)| {
+                    debug!("Spawning: {level_handle:?}");
+                    commands.entity(entity).with_children(|parent| {
+                        parent.spawn(level_handle.clone());
+                    });
+                });
+
+            spawned
+                .iter()
+                .filter(|(_, spawned_handle)| {
+                    !selected.iter().any(|(level_handle, selected)| {
+                        *selected && level_handle.id() == spawned_handle.id()
+                    })
+                })
+                .for_each(|(level_entity, _)| {
+                    debug!("Despawning: {level_entity:?}");
+                    commands.entity(*level_entity).remove_parent();
+                    commands.entity(*level_entity).despawn_recursive();
+                });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+
+use crate::system_params::level::LdtkLevelCommands;
+
+// Add to any entity (a player, a camera) whose movement between levels should be reported
+// through LevelTransitionEvent.
+#[derive(Clone, Component, Debug, Default, Reflect)]
+pub struct LevelTracker {
+    pub(crate) level_iid: Option<String>,
+}
+
+// Sent when a tracked entity leaves one level's bounds for another. Either side is None when
+// the entity is outside of every level.
+#[derive(Clone, Debug, Event)]
+pub struct LevelTransitionEvent {
+    pub entity: Entity,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl LevelTracker {
+    pub fn level_iid(&self) -> Option<&str> {
+        self.level_iid.as_deref()
+    }
+
+    // While the entity stays within its current level, overlapping levels don't cause a
+    // transition.
+    pub(crate) fn level_tracker_system(
+        mut query: Query<(Entity, &GlobalTransform, &mut LevelTracker)>,
+        mut events: EventWriter<LevelTransitionEvent>,
+        level_commands: LdtkLevelCommands,
+    ) {
+        for (entity, global_transform, mut tracker) in query.iter_mut() {
+            let point = global_transform.translation().truncate();
+
+            let still_inside = tracker
+                .level_iid
+                .as_ref()
+                .and_then(|iid| level_commands.with_iid(iid))
+                .is_some_and(|level_asset| level_asset.contains(point));
+
+            if still_inside {
+                continue;
+            }
+
+            let to = level_commands
+                .level_at(point)
+                .map(|level_asset| level_asset.iid.clone());
+
+            if to != tracker.level_iid {
+                events.send(LevelTransitionEvent {
+                    entity,
+                    from: tracker.level_iid.clone(),
+                    to: to.clone(),
+                });
+
+                tracker.level_iid = to;
+            }
+        }
+    }
+}
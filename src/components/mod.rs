@@ -1,5 +1,13 @@
+pub(crate) mod auto_layer;
+pub(crate) mod entity_refs;
 pub(crate) mod field_instances;
 pub(crate) mod iid;
+pub(crate) mod int_grid;
+pub(crate) mod int_grid_colliders;
+pub(crate) mod level_selection;
+pub(crate) mod level_tracker;
+pub(crate) mod parallax;
+pub(crate) mod snapshot;
 pub(crate) mod tiles;
 pub(crate) mod tileset_rectangle;
 pub(crate) mod traits;
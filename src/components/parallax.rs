@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy::transform::helper::ComputeGlobalTransformError;
+use bevy::transform::helper::TransformHelper;
+use thiserror::Error;
+
+use crate::assets::layer::LayerAsset;
+use crate::assets::level::LevelAsset;
+
+#[derive(Debug, Error)]
+pub enum ParallaxError {
+    #[error(transparent)]
+    ComputeGlobalTransformError(#[from] ComputeGlobalTransformError),
+    #[error("Bad handle?")]
+    BadHandle,
+    #[error("Layer parent is not a level?")]
+    ParentNotLevel,
+}
+
+// Add to the camera that layers should be offset against. Parallax is disabled while no
+// camera carries this marker.
+#[derive(Clone, Component, Debug, Default, Reflect)]
+pub struct ParallaxCamera;
+
+// From LayerDefinition::parallax_factor_x/y and parallax_scaling
+#[derive(Clone, Component, Debug, Reflect)]
+pub struct Parallax {
+    pub factor: Vec2,
+    pub scaling: bool,
+}
+
+impl Parallax {
+    // Like the LDtk editor, layers are shifted by the camera's distance from the level center
+    // times the parallax factor, and optionally scaled about the level center by 1 - factor.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn parallax_system(
+        cameras: Query<Entity, With<ParallaxCamera>>,
+        layers: Query<(Entity, &Handle<LayerAsset>, &Parallax, &Parent)>,
+        levels: Query<&Handle<LevelAsset>>,
+        mut transforms: ParamSet<(TransformHelper, Query<&mut Transform, With<Parallax>>)>,
+        layer_assets: Res<Assets<LayerAsset>>,
+        level_assets: Res<Assets<LevelAsset>>,
+    ) -> Result<(), ParallaxError> {
+        let Ok(camera) = cameras.get_single() else {
+            return Ok(());
+        };
+
+        let camera_location = transforms
+            .p0()
+            .compute_global_transform(camera)?
+            .translation()
+            .truncate();
+
+        let mut updates = vec![];
+
+        for (entity, layer_handle, parallax, parent) in layers.iter() {
+            let layer_asset = layer_assets
+                .get(layer_handle)
+                .ok_or(ParallaxError::BadHandle)?;
+
+            let level_asset = levels
+                .get(parent.get())
+                .map_err(|_| ParallaxError::ParentNotLevel)
+                .and_then(|level_handle| {
+                    level_assets
+                        .get(level_handle)
+                        .ok_or(ParallaxError::BadHandle)
+                })?;
+
+            let level_location = transforms
+                .p0()
+                .compute_global_transform(parent.get())?
+                .translation()
+                .truncate();
+
+            let level_center = Vec2::new(level_asset.size.x, -level_asset.size.y) / 2.0;
+
+            let offset = (camera_location - (level_location + level_center)) * parallax.factor;
+
+            let scale = if parallax.scaling {
+                Vec2::ONE - parallax.factor
+            } else {
+                Vec2::ONE
+            };
+
+            let location =
+                level_center + (layer_asset.location.truncate() - level_center) * scale + offset;
+
+            updates.push((
+                entity,
+                location.extend(layer_asset.location.z),
+                scale.extend(1.0),
+            ));
+        }
+
+        let mut query = transforms.p1();
+
+        for (entity, translation, scale) in updates {
+            if let Ok(mut transform) = query.get_mut(entity) {
+                transform.translation = translation;
+                transform.scale = scale;
+            }
+        }
+
+        Ok(())
+    }
+}
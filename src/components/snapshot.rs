@@ -0,0 +1,290 @@
+use bevy::ecs::world::EntityRef;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+use crate::assets::entity::EntityAsset;
+use crate::assets::layer::LayerAsset;
+use crate::assets::traits::LdtkAssetLoadEvent;
+use crate::components::iid::Iid;
+use crate::components::iid::IidMap;
+
+#[derive(Debug, Error)]
+pub enum LdtkSnapshotError {
+    #[error("Unable to save {key} of {iid}! {source}")]
+    BadComponent {
+        key: String,
+        iid: String,
+        source: serde_json::Error,
+    },
+}
+
+// Runtime state of LDtk entities by iid, to be saved with a game. Despawned LDtk entities are
+// recorded as they go, transforms and registered components whenever a snapshot is taken.
+// Whatever is in the resource is applied to LDtk entities as they spawn, so insert a saved
+// snapshot before loading the project, and reset it for a new game.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Resource, Serialize)]
+pub struct LdtkSnapshot {
+    pub despawned: BTreeSet<String>,
+    pub entities: BTreeMap<String, LdtkEntitySnapshot>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LdtkEntitySnapshot {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    // Registered components, by key
+    pub components: BTreeMap<String, serde_json::Value>,
+}
+
+type RecordComponent = fn(&EntityRef) -> Option<Result<serde_json::Value, serde_json::Error>>;
+type RestoreComponent = fn(&mut EntityWorldMut, serde_json::Value) -> Result<(), serde_json::Error>;
+
+pub(crate) struct LdtkSnapshotComponent {
+    pub(crate) key: String,
+    record: RecordComponent,
+    restore: RestoreComponent,
+}
+
+// Components saved with snapshots, see LdtkAppEx::register_ldtk_snapshot_component
+#[derive(Default, Resource)]
+pub(crate) struct LdtkSnapshotComponents {
+    pub(crate) components: Vec<LdtkSnapshotComponent>,
+}
+
+impl LdtkSnapshotComponent {
+    pub(crate) fn new<T>(key: &str) -> Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        Self {
+            key: key.to_owned(),
+            record: |entity_ref| entity_ref.get::<T>().map(serde_json::to_value),
+            restore: |entity_mut, value| {
+                entity_mut.insert(serde_json::from_value::<T>(value)?);
+                Ok(())
+            },
+        }
+    }
+}
+
+impl LdtkSnapshot {
+    // Records the transform and registered components of every spawned LDtk entity, keeping
+    // what was recorded for entities that aren't spawned now.
+    pub fn take(world: &mut World) -> Result<(), LdtkSnapshotError> {
+        let mut query = world.query_filtered::<(EntityRef, &Iid), With<Handle<EntityAsset>>>();
+        let components = world.get_resource::<LdtkSnapshotComponents>();
+
+        let mut entities = Vec::new();
+
+        for (entity_ref, iid) in query.iter(world) {
+            let Some(transform) = entity_ref.get::<Transform>() else {
+                continue;
+            };
+
+            let mut entity_snapshot = LdtkEntitySnapshot {
+                translation: transform.translation,
+                rotation: transform.rotation,
+                scale: transform.scale,
+                components: BTreeMap::new(),
+            };
+
+            for component in components
+                .iter()
+                .flat_map(|components| &components.components)
+            {
+                let Some(value) = (component.record)(&entity_ref) else {
+                    continue;
+                };
+
+                let value = value.map_err(|source| LdtkSnapshotError::BadComponent {
+                    key: component.key.clone(),
+                    iid: iid.to_string(),
+                    source,
+                })?;
+
+                entity_snapshot
+                    .components
+                    .insert(component.key.clone(), value);
+            }
+
+            entities.push((iid.to_string(), entity_snapshot));
+        }
+
+        world
+            .get_resource_or_insert_with(LdtkSnapshot::default)
+            .entities
+            .extend(entities);
+
+        Ok(())
+    }
+
+    fn restore(world: &mut World, entity: Entity, entity_snapshot: LdtkEntitySnapshot) {
+        world.resource_scope(|world, components: Mut<LdtkSnapshotComponents>| {
+            let Some(mut entity_mut) = world.get_entity_mut(entity) else {
+                return;
+            };
+
+            if let Some(mut transform) = entity_mut.get_mut::<Transform>() {
+                transform.translation = entity_snapshot.translation;
+                transform.rotation = entity_snapshot.rotation;
+                transform.scale = entity_snapshot.scale;
+            }
+
+            for component in &components.components {
+                let Some(value) = entity_snapshot.components.get(&component.key) else {
+                    continue;
+                };
+
+                if let Err(e) = (component.restore)(&mut entity_mut, value.clone()) {
+                    error!("Unable to restore {}! {e}", component.key);
+                }
+            }
+        });
+    }
+
+    // LDtk entities despawned while their layer is still spawned were destroyed by gameplay.
+    // Anything despawned with its layer was only unloaded.
+    pub(crate) fn snapshot_despawn_system(
+        mut snapshot: ResMut<LdtkSnapshot>,
+        mut removed_iids: RemovedComponents<Iid>,
+        layer_query: Query<(), With<Handle<LayerAsset>>>,
+        iid_map: Res<IidMap>,
+        entity_assets: Res<Assets<EntityAsset>>,
+    ) {
+        for entity in removed_iids.read() {
+            let Some(iid) = iid_map.iid(entity) else {
+                continue;
+            };
+
+            let Some(entity_asset) = iid_map
+                .handle::<EntityAsset>(iid.as_str())
+                .and_then(|handle| entity_assets.get(handle))
+            else {
+                continue;
+            };
+
+            let layer_spawned = iid_map
+                .entity(&entity_asset.layer_iid)
+                .is_some_and(|layer| layer_query.contains(layer));
+
+            if layer_spawned {
+                snapshot.entities.remove(iid.as_str());
+                snapshot.despawned.insert(iid.to_string());
+            }
+        }
+    }
+
+    // Applied a frame after the entity spawns, once every system reacting to the spawn has
+    // inserted its components.
+    pub(crate) fn snapshot_restore_system(
+        mut commands: Commands,
+        mut events: EventReader<LdtkAssetLoadEvent<EntityAsset>>,
+        mut pending: Local<Vec<Entity>>,
+        query: Query<Ref<Iid>>,
+        snapshot: Res<LdtkSnapshot>,
+    ) {
+        for entity in pending.drain(..) {
+            let Ok(iid) = query.get(entity) else {
+                continue;
+            };
+
+            if snapshot.despawned.contains(iid.as_str()) {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+
+            if let Some(entity_snapshot) = snapshot.entities.get(iid.as_str()).cloned() {
+                commands.add(move |world: &mut World| {
+                    LdtkSnapshot::restore(world, entity, entity_snapshot);
+                });
+            }
+        }
+
+        // Reloads of an already spawned entity keep its runtime state as it is
+        pending.extend(
+            events
+                .read()
+                .map(|event| event.entity)
+                .filter(|entity| query.get(*entity).is_ok_and(|iid| iid.is_added())),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Debug, Deserialize, PartialEq, Serialize)]
+    struct Health(i32);
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<LdtkSnapshot>();
+        world.insert_resource(LdtkSnapshotComponents {
+            components: vec![LdtkSnapshotComponent::new::<Health>("health")],
+        });
+        world
+    }
+
+    #[test]
+    fn take_and_restore() {
+        let mut world = world();
+        world.resource_mut::<LdtkSnapshot>().entities.insert(
+            "unloaded".to_owned(),
+            LdtkEntitySnapshot {
+                translation: Vec3::ONE,
+                rotation: Quat::IDENTITY,
+                scale: Vec3::ONE,
+                components: BTreeMap::new(),
+            },
+        );
+
+        world.spawn((
+            Handle::<EntityAsset>::default(),
+            Iid::new("moved"),
+            Transform::from_xyz(3.0, -4.0, 0.0).with_scale(Vec3::splat(2.0)),
+            Health(7),
+        ));
+        world.spawn((
+            Handle::<EntityAsset>::default(),
+            Iid::new("still"),
+            Transform::from_xyz(5.0, -6.0, 0.0),
+        ));
+
+        LdtkSnapshot::take(&mut world).unwrap();
+
+        let snapshot = world.resource::<LdtkSnapshot>().clone();
+        assert_eq!(snapshot.entities.len(), 3);
+        assert!(snapshot.entities.contains_key("unloaded"));
+        assert_eq!(
+            snapshot.entities["moved"].components["health"],
+            serde_json::json!(7)
+        );
+        assert!(snapshot.entities["still"].components.is_empty());
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            serde_json::from_str::<LdtkSnapshot>(&json).unwrap(),
+            snapshot
+        );
+
+        let mut world = self::world();
+        let entity = world
+            .spawn((Transform::from_xyz(1.0, 1.0, 0.0), Health(10)))
+            .id();
+
+        LdtkSnapshot::restore(&mut world, entity, snapshot.entities["moved"].clone());
+
+        assert_eq!(
+            *world.get::<Transform>(entity).unwrap(),
+            Transform::from_xyz(3.0, -4.0, 0.0).with_scale(Vec3::splat(2.0))
+        );
+        assert_eq!(world.get::<Health>(entity), Some(&Health(7)));
+    }
+}
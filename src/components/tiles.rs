@@ -1,4 +1,6 @@
+use bevy::math::I64Vec2;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 
 use crate::exports::tile_instance::TileInstance;
 
@@ -6,3 +8,51 @@ use crate::exports::tile_instance::TileInstance;
 pub struct Tiles {
     pub tiles: Vec<TileInstance>,
 }
+
+// Pixel regions of the layer's image to redraw on the next change to its Tiles, instead of the
+// whole layer.
+#[derive(Clone, Component, Debug, Default)]
+pub(crate) struct TilesDirtyRegions(pub(crate) Vec<IRect>);
+
+impl TilesDirtyRegions {
+    pub(crate) fn add(world: &mut World, entity: Entity, region: IRect) {
+        if region.is_empty() {
+            return;
+        }
+
+        match world.get_mut::<TilesDirtyRegions>(entity) {
+            Some(mut regions) => regions.0.push(region),
+            None => {
+                if let Some(mut entity) = world.get_entity_mut(entity) {
+                    entity.insert(TilesDirtyRegions(vec![region]));
+                }
+            }
+        }
+    }
+}
+
+// The quads of a layer mesh drawing the tiles at each location, so that edits can rewrite them
+// in place. Quads of removed tiles are left empty until another tile takes them.
+#[derive(Clone, Component, Debug, Default)]
+pub(crate) struct TileMeshQuads {
+    pub(crate) locations: HashMap<I64Vec2, Vec<u32>>,
+    pub(crate) free: Vec<u32>,
+}
+
+impl TileMeshQuads {
+    // As laid out by build_mesh_from_tiles, one quad per tile in order
+    pub(crate) fn new(tiles: &Tiles) -> Self {
+        let mut locations = HashMap::<I64Vec2, Vec<u32>>::default();
+        for (quad, tile) in tiles.tiles.iter().enumerate() {
+            locations
+                .entry(tile.location)
+                .or_default()
+                .push(quad as u32);
+        }
+
+        Self {
+            locations,
+            free: vec![],
+        }
+    }
+}
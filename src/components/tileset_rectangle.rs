@@ -1,8 +1,10 @@
 use bevy::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::ldtk;
 
-#[derive(Clone, Component, Debug, Reflect)]
+#[derive(Clone, Component, Debug, Deserialize, Reflect, Serialize)]
 pub struct TilesetRectangle {
     pub location: Vec2,
     pub size: Vec2,
@@ -0,0 +1,113 @@
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+
+use crate::ldtk;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum AutoLayerRuleChecker {
+    Horizontal,
+    None,
+    Vertical,
+}
+
+impl AutoLayerRuleChecker {
+    pub(crate) fn new(value: &ldtk::Checker) -> Self {
+        match value {
+            ldtk::Checker::Horizontal => Self::Horizontal,
+            ldtk::Checker::None => Self::None,
+            ldtk::Checker::Vertical => Self::Vertical,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum AutoLayerRuleTileMode {
+    Single,
+    Stamp,
+}
+
+impl AutoLayerRuleTileMode {
+    pub(crate) fn new(value: &ldtk::TileMode) -> Self {
+        match value {
+            ldtk::TileMode::Single => Self::Single,
+            ldtk::TileMode::Stamp => Self::Stamp,
+        }
+    }
+}
+
+// Perlin masks (perlinActive and friends) aren't supported.
+#[derive(Clone, Debug, Reflect)]
+pub struct AutoLayerRule {
+    pub active: bool,
+    pub alpha: f32,
+    pub break_on_match: bool,
+    pub chance: f64,
+    pub checker: AutoLayerRuleChecker,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub out_of_bounds_value: Option<i64>,
+    pub pattern: Vec<i64>,
+    pub pivot: Vec2,
+    pub size: i64,
+    pub tile_mode: AutoLayerRuleTileMode,
+    pub tile_offset: I64Vec2,
+    pub tile_random_x: (i64, i64),
+    pub tile_random_y: (i64, i64),
+    pub tile_rects_ids: Vec<Vec<i64>>,
+    pub uid: i64,
+    pub modulo: I64Vec2,
+    pub offset: I64Vec2,
+}
+
+impl AutoLayerRule {
+    pub(crate) fn new(value: &ldtk::AutoLayerRuleDefinition) -> Self {
+        Self {
+            active: value.active,
+            alpha: value.alpha as f32,
+            break_on_match: value.break_on_match,
+            chance: value.chance,
+            checker: AutoLayerRuleChecker::new(&value.checker),
+            flip_x: value.flip_x,
+            flip_y: value.flip_y,
+            out_of_bounds_value: value.out_of_bounds_value,
+            pattern: value.pattern.clone(),
+            pivot: (value.pivot_x as f32, value.pivot_y as f32).into(),
+            size: value.size,
+            tile_mode: AutoLayerRuleTileMode::new(&value.tile_mode),
+            tile_offset: (value.tile_x_offset, value.tile_y_offset).into(),
+            tile_random_x: (value.tile_random_x_min, value.tile_random_x_max),
+            tile_random_y: (value.tile_random_y_min, value.tile_random_y_max),
+            tile_rects_ids: value.tile_rects_ids.clone(),
+            uid: value.uid,
+            // LDtk never exports a modulo below 1, but a zero would panic below
+            modulo: (value.x_modulo.max(1), value.y_modulo.max(1)).into(),
+            offset: (value.x_offset, value.y_offset).into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Reflect)]
+pub struct AutoLayerRuleGroup {
+    pub active: bool,
+    // 0: every required biome value must be set on the level, 1: any of them
+    pub biome_requirement_mode: i64,
+    pub is_optional: bool,
+    pub name: String,
+    pub required_biome_values: Vec<String>,
+    pub rules: Vec<AutoLayerRule>,
+    pub uid: i64,
+}
+
+impl AutoLayerRuleGroup {
+    pub(crate) fn new(value: &ldtk::AutoLayerRuleGroup) -> Self {
+        Self {
+            active: value.active,
+            biome_requirement_mode: value.biome_requirement_mode,
+            is_optional: value.is_optional,
+            name: value.name.clone(),
+            required_biome_values: value.required_biome_values.clone(),
+            rules: value.rules.iter().map(AutoLayerRule::new).collect(),
+            uid: value.uid,
+        }
+    }
+}
@@ -5,7 +5,7 @@ use crate::ldtk;
 use crate::util::bevy_color_from_ldtk;
 use crate::util::ColorParseError;
 
-#[derive(Debug, Default, Reflect)]
+#[derive(Clone, Debug, Default, Reflect)]
 pub struct IntGridValueGroup {
     pub color: Option<Color>,
     pub identifier: Option<String>,
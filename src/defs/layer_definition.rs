@@ -3,6 +3,7 @@ use thiserror::Error;
 
 use crate::assets::layer::LayerAssetError;
 use crate::assets::layer::LayerType;
+use crate::defs::auto_layer_rule::AutoLayerRuleGroup;
 use crate::defs::int_grid_value::IntGridValue;
 use crate::defs::int_grid_value::IntGridValueFromError;
 use crate::defs::int_grid_value_group::IntGridValueGroup;
@@ -12,7 +13,9 @@ use crate::ldtk;
 #[derive(Debug, Reflect)]
 pub struct LayerDefinition {
     pub layer_definition_type: LayerType,
+    pub auto_rule_groups: Vec<AutoLayerRuleGroup>,
     pub auto_source_layer_def_uid: Option<i64>,
+    pub biome_field_uid: Option<i64>,
     pub display_opacity: f64,
     pub grid_cell_size: i64,
     pub identifier: String,
@@ -22,6 +25,7 @@ pub struct LayerDefinition {
     pub parallax_factor_y: f64,
     pub parallax_scaling: bool,
     pub offset: Vec2,
+    pub tile_pivot: Vec2,
     pub tileset_def_uid: Option<i64>,
     pub uid: i64,
 }
@@ -41,7 +45,13 @@ impl LayerDefinition {
     pub(crate) fn new(value: &ldtk::LayerDefinition) -> Result<Self, LayerDefinitionFromError> {
         Ok(Self {
             layer_definition_type: LayerType::new(&value.layer_definition_type)?,
+            auto_rule_groups: value
+                .auto_rule_groups
+                .iter()
+                .map(AutoLayerRuleGroup::new)
+                .collect(),
             auto_source_layer_def_uid: value.auto_source_layer_def_uid,
+            biome_field_uid: value.biome_field_uid,
             display_opacity: value.display_opacity,
             grid_cell_size: value.grid_size,
             identifier: value.identifier.clone(),
@@ -59,6 +69,7 @@ impl LayerDefinition {
             parallax_factor_y: value.parallax_factor_y,
             parallax_scaling: value.parallax_scaling,
             offset: (value.px_offset_x as f32, value.px_offset_y as f32).into(),
+            tile_pivot: (value.tile_pivot_x as f32, value.tile_pivot_y as f32).into(),
             tileset_def_uid: value.tileset_def_uid,
             uid: value.uid,
         })
@@ -1,3 +1,4 @@
+pub(crate) mod auto_layer_rule;
 pub(crate) mod entity_definition;
 pub(crate) mod enum_definition;
 pub(crate) mod enum_tag_value;
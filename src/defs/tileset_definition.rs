@@ -41,4 +41,18 @@ impl TilesetDefinition {
             uid: value.uid,
         }
     }
+
+    // Tile ids count left to right, then top to bottom
+    pub fn tile_cell(&self, tile_id: i64) -> I64Vec2 {
+        let columns = self.grid_size.x.max(1);
+        (tile_id % columns, tile_id / columns).into()
+    }
+
+    pub fn tile_source(&self, tile_id: i64) -> UVec2 {
+        (self.tile_cell(tile_id) * (self.tile_grid_size + self.spacing) + self.padding).as_uvec2()
+    }
+
+    pub fn contains_tile(&self, tile_id: i64) -> bool {
+        (0..self.grid_size.x * self.grid_size.y).contains(&tile_id)
+    }
 }
@@ -1,8 +1,15 @@
+use bevy::math::I64Vec2;
 use bevy::prelude::*;
+use serde::Serialize;
+use serde_json::json;
 use thiserror::Error;
 
 use crate::components::tileset_rectangle::TilesetRectangle;
+use crate::exports::reference_to_an_entity_instance::ReferenceToAnEntityInstance;
 use crate::ldtk;
+use crate::util::bevy_color_from_ldtk;
+use crate::util::ldtk_color_from_bevy;
+use crate::util::ColorParseError;
 
 #[derive(Debug, Error)]
 pub enum FieldInstanceValueParseError {
@@ -18,33 +25,48 @@ pub enum FieldInstanceValueParseError {
     BadString,
     #[error("Unable to parse as a boolean?!")]
     BadBool,
-    #[error("Unable to parse as a TilesetRectangle? serde_json error: {0:?}")]
-    BadTile(#[from] serde_json::error::Error),
+    #[error("Unable to parse as an array?!")]
+    BadArray,
+    #[error(transparent)]
+    BadColor(#[from] ColorParseError),
+    #[error("Unable to deserialize value? serde_json error: {0:?}")]
+    BadJson(#[from] serde_json::error::Error),
 }
 
 #[derive(Debug, Error)]
-pub enum FieldInstanceValueAsTileError {
+pub enum FieldInstanceValueAsError {
     #[error("Parse error! {0:?}")]
     ParseError(#[from] FieldInstanceValueParseError),
     #[error("Wrong type!")]
     WrongType,
 }
 
-#[derive(Clone, Debug, Reflect)]
-// TODO: fill me out!
+#[derive(Clone, Debug, Reflect, Serialize)]
+#[serde(untagged)]
 pub enum FieldInstanceValue {
-    Int(i64),
-    Float(f64),
+    Int(Option<i64>),
+    Float(Option<f64>),
     String(Option<String>),
-    Multilines(String),
-    Enum(String),
+    Multilines(Option<String>),
     Bool(bool),
-    Tile(TilesetRectangle),
-    ArrayTile(Vec<TilesetRectangle>),
+    Color(Option<Color>),
+    FilePath(Option<String>),
+    Enum(Option<String>),
+    Tile(Option<TilesetRectangle>),
+    EntityRef(Option<ReferenceToAnEntityInstance>),
     // from GridPoint
-    // GridPoint(U64Vec2),
-    // EntityReferenceInfo(ReferenceToAnEntityInstance),
-    // Array(Vec<FieldInstanceValue>),
+    Point(Option<I64Vec2>),
+    ArrayInt(Vec<Option<i64>>),
+    ArrayFloat(Vec<Option<f64>>),
+    ArrayString(Vec<Option<String>>),
+    ArrayMultilines(Vec<Option<String>>),
+    ArrayBool(Vec<bool>),
+    ArrayColor(Vec<Option<Color>>),
+    ArrayFilePath(Vec<Option<String>>),
+    ArrayEnum(Vec<Option<String>>),
+    ArrayTile(Vec<Option<TilesetRectangle>>),
+    ArrayEntityRef(Vec<Option<ReferenceToAnEntityInstance>>),
+    ArrayPoint(Vec<Option<I64Vec2>>),
 }
 
 #[derive(Clone, Debug, Reflect)]
@@ -56,11 +78,329 @@ pub struct FieldInstance {
 }
 
 impl FieldInstance {
-    pub fn as_tile(&self) -> Result<&TilesetRectangle, FieldInstanceValueAsTileError> {
+    pub fn as_int(&self) -> Result<Option<i64>, FieldInstanceValueAsError> {
+        if let FieldInstanceValue::Int(int) = &self.value {
+            Ok(*int)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_float(&self) -> Result<Option<f64>, FieldInstanceValueAsError> {
+        if let FieldInstanceValue::Float(float) = &self.value {
+            Ok(*float)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    // Multilines is only a presentation hint in LDtk, so treat it as a string here
+    pub fn as_string(&self) -> Result<Option<&str>, FieldInstanceValueAsError> {
+        match &self.value {
+            FieldInstanceValue::String(string) | FieldInstanceValue::Multilines(string) => {
+                Ok(string.as_deref())
+            }
+            _ => Err(FieldInstanceValueAsError::WrongType),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, FieldInstanceValueAsError> {
+        if let FieldInstanceValue::Bool(bool) = &self.value {
+            Ok(*bool)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_color(&self) -> Result<Option<Color>, FieldInstanceValueAsError> {
+        if let FieldInstanceValue::Color(color) = &self.value {
+            Ok(*color)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_file_path(&self) -> Result<Option<&str>, FieldInstanceValueAsError> {
+        if let FieldInstanceValue::FilePath(file_path) = &self.value {
+            Ok(file_path.as_deref())
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_enum(&self) -> Result<Option<&str>, FieldInstanceValueAsError> {
+        if let FieldInstanceValue::Enum(enum_value) = &self.value {
+            Ok(enum_value.as_deref())
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_tile(&self) -> Result<Option<&TilesetRectangle>, FieldInstanceValueAsError> {
         if let FieldInstanceValue::Tile(tile) = &self.value {
-            Ok(tile)
+            Ok(tile.as_ref())
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_entity_ref(
+        &self,
+    ) -> Result<Option<&ReferenceToAnEntityInstance>, FieldInstanceValueAsError> {
+        if let FieldInstanceValue::EntityRef(entity_ref) = &self.value {
+            Ok(entity_ref.as_ref())
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_point(&self) -> Result<Option<I64Vec2>, FieldInstanceValueAsError> {
+        if let FieldInstanceValue::Point(point) = &self.value {
+            Ok(*point)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_int(&self) -> Result<&[Option<i64>], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayInt(array) = &self.value {
+            Ok(array)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_float(&self) -> Result<&[Option<f64>], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayFloat(array) = &self.value {
+            Ok(array)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_string(&self) -> Result<&[Option<String>], FieldInstanceValueAsError> {
+        match &self.value {
+            FieldInstanceValue::ArrayString(array) | FieldInstanceValue::ArrayMultilines(array) => {
+                Ok(array)
+            }
+            _ => Err(FieldInstanceValueAsError::WrongType),
+        }
+    }
+
+    pub fn as_array_bool(&self) -> Result<&[bool], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayBool(array) = &self.value {
+            Ok(array)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_color(&self) -> Result<&[Option<Color>], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayColor(array) = &self.value {
+            Ok(array)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_file_path(&self) -> Result<&[Option<String>], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayFilePath(array) = &self.value {
+            Ok(array)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_enum(&self) -> Result<&[Option<String>], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayEnum(array) = &self.value {
+            Ok(array)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_tile(&self) -> Result<&[Option<TilesetRectangle>], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayTile(array) = &self.value {
+            Ok(array)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_entity_ref(
+        &self,
+    ) -> Result<&[Option<ReferenceToAnEntityInstance>], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayEntityRef(array) = &self.value {
+            Ok(array)
+        } else {
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+
+    pub fn as_array_point(&self) -> Result<&[Option<I64Vec2>], FieldInstanceValueAsError> {
+        if let FieldInstanceValue::ArrayPoint(array) = &self.value {
+            Ok(array)
         } else {
-            Err(FieldInstanceValueAsTileError::WrongType)
+            Err(FieldInstanceValueAsError::WrongType)
+        }
+    }
+}
+
+// An object keyed by field identifier, suitable for deserializing into user types with serde
+pub(crate) fn field_instances_to_json(
+    field_instances: &[FieldInstance],
+) -> Result<serde_json::Value, serde_json::Error> {
+    Ok(serde_json::Value::Object(
+        field_instances
+            .iter()
+            .map(|field_instance| {
+                Ok((
+                    field_instance.identifier.clone(),
+                    serde_json::to_value(&field_instance.value)?,
+                ))
+            })
+            .collect::<Result<_, serde_json::Error>>()?,
+    ))
+}
+
+impl FieldInstanceValue {
+    // The __value and realEditorValues LDtk would save. The editor only reads realEditorValues,
+    // which hold one V_Int, V_Float, V_Bool or V_String wrapper per element.
+    pub(crate) fn to_ldtk(&self) -> (serde_json::Value, Vec<Option<serde_json::Value>>) {
+        fn wrap(id: &str, value: serde_json::Value) -> Option<serde_json::Value> {
+            Some(json!({ "id": id, "params": [value] }))
+        }
+
+        fn int(value: &Option<i64>) -> (serde_json::Value, Option<serde_json::Value>) {
+            (
+                json!(value),
+                value.and_then(|value| wrap("V_Int", json!(value))),
+            )
+        }
+
+        fn float(value: &Option<f64>) -> (serde_json::Value, Option<serde_json::Value>) {
+            (
+                json!(value),
+                value.and_then(|value| wrap("V_Float", json!(value))),
+            )
+        }
+
+        fn bool(value: &bool) -> (serde_json::Value, Option<serde_json::Value>) {
+            (json!(value), wrap("V_Bool", json!(value)))
+        }
+
+        fn string(value: &Option<String>) -> (serde_json::Value, Option<serde_json::Value>) {
+            (
+                json!(value),
+                value
+                    .as_ref()
+                    .and_then(|value| wrap("V_String", json!(value))),
+            )
+        }
+
+        // Colors are saved as 0xRRGGBB integers
+        fn color(value: &Option<Color>) -> (serde_json::Value, Option<serde_json::Value>) {
+            let Some(color) = value else {
+                return (serde_json::Value::Null, None);
+            };
+            let [red, green, blue, _] = color.as_rgba_u8();
+            let int = (red as i64) << 16 | (green as i64) << 8 | blue as i64;
+            (
+                json!(ldtk_color_from_bevy(color)),
+                wrap("V_Int", json!(int)),
+            )
+        }
+
+        // The tileset is taken from the field definition
+        fn tile(
+            value: &Option<TilesetRectangle>,
+        ) -> (serde_json::Value, Option<serde_json::Value>) {
+            let Some(value) = value else {
+                return (serde_json::Value::Null, None);
+            };
+            let (location, size) = (value.location.as_ivec2(), value.size.as_ivec2());
+            (
+                json!({
+                    "tilesetUid": value.tileset_uid,
+                    "x": location.x,
+                    "y": location.y,
+                    "w": size.x,
+                    "h": size.y,
+                }),
+                wrap(
+                    "V_String",
+                    json!(format!(
+                        "{},{},{},{}",
+                        location.x, location.y, size.x, size.y
+                    )),
+                ),
+            )
+        }
+
+        fn entity_ref(
+            value: &Option<ReferenceToAnEntityInstance>,
+        ) -> (serde_json::Value, Option<serde_json::Value>) {
+            let Some(value) = value else {
+                return (serde_json::Value::Null, None);
+            };
+            (
+                json!({
+                    "entityIid": value.entity_iid,
+                    "layerIid": value.layer_iid,
+                    "levelIid": value.level_iid,
+                    "worldIid": value.world_iid,
+                }),
+                wrap("V_String", json!(value.entity_iid)),
+            )
+        }
+
+        fn point(value: &Option<I64Vec2>) -> (serde_json::Value, Option<serde_json::Value>) {
+            let Some(value) = value else {
+                return (serde_json::Value::Null, None);
+            };
+            (
+                json!({ "cx": value.x, "cy": value.y }),
+                wrap("V_String", json!(format!("{},{}", value.x, value.y))),
+            )
+        }
+
+        fn single(
+            (value, editor_value): (serde_json::Value, Option<serde_json::Value>),
+        ) -> (serde_json::Value, Vec<Option<serde_json::Value>>) {
+            (value, vec![editor_value])
+        }
+
+        fn array<T>(
+            values: &[T],
+            element: impl Fn(&T) -> (serde_json::Value, Option<serde_json::Value>),
+        ) -> (serde_json::Value, Vec<Option<serde_json::Value>>) {
+            let (values, editor_values) = values.iter().map(element).unzip();
+            (serde_json::Value::Array(values), editor_values)
+        }
+
+        match self {
+            FieldInstanceValue::Int(value) => single(int(value)),
+            FieldInstanceValue::Float(value) => single(float(value)),
+            FieldInstanceValue::String(value)
+            | FieldInstanceValue::Multilines(value)
+            | FieldInstanceValue::FilePath(value)
+            | FieldInstanceValue::Enum(value) => single(string(value)),
+            FieldInstanceValue::Bool(value) => single(bool(value)),
+            FieldInstanceValue::Color(value) => single(color(value)),
+            FieldInstanceValue::Tile(value) => single(tile(value)),
+            FieldInstanceValue::EntityRef(value) => single(entity_ref(value)),
+            FieldInstanceValue::Point(value) => single(point(value)),
+            FieldInstanceValue::ArrayInt(values) => array(values, int),
+            FieldInstanceValue::ArrayFloat(values) => array(values, float),
+            FieldInstanceValue::ArrayString(values)
+            | FieldInstanceValue::ArrayMultilines(values)
+            | FieldInstanceValue::ArrayFilePath(values)
+            | FieldInstanceValue::ArrayEnum(values) => array(values, string),
+            FieldInstanceValue::ArrayBool(values) => array(values, bool),
+            FieldInstanceValue::ArrayColor(values) => array(values, color),
+            FieldInstanceValue::ArrayTile(values) => array(values, tile),
+            FieldInstanceValue::ArrayEntityRef(values) => array(values, entity_ref),
+            FieldInstanceValue::ArrayPoint(values) => array(values, point),
         }
     }
 }
@@ -90,56 +430,156 @@ impl FieldInstance {
         Ok(Self {
             identifier: value.identifier.clone(),
             tile: value.tile.as_ref().map(TilesetRectangle::new),
-            value: {
-                let field_instance_type = value.field_instance_type.as_str();
-                let value = value
-                    .value
-                    .as_ref()
-                    .ok_or(FieldInstanceValueParseError::ValueIsNone)?;
-                match field_instance_type {
-                    "Int" => FieldInstanceValue::Int(
-                        value.as_i64().ok_or(FieldInstanceValueParseError::BadInt)?,
-                    ),
-                    "Float" => FieldInstanceValue::Float(
-                        value
-                            .as_f64()
-                            .ok_or(FieldInstanceValueParseError::BadFloat)?,
-                    ),
-                    "String" => {
-                        FieldInstanceValue::String(value.as_str().map(|str| str.to_string()))
-                    }
-                    "Multilines" => FieldInstanceValue::Multilines(
-                        value
-                            .as_str()
-                            .ok_or(FieldInstanceValueParseError::BadString)?
-                            .to_owned(),
-                    ),
-                    "Bool" => FieldInstanceValue::Bool(
-                        value
-                            .as_bool()
-                            .ok_or(FieldInstanceValueParseError::BadBool)?,
-                    ),
-                    "Tile" => {
-                        let ldtk_tile: ldtk::TilesetRectangle =
-                            serde_json::from_value(value.clone())?;
-                        let tile = TilesetRectangle::new(&ldtk_tile);
-                        FieldInstanceValue::Tile(tile)
-                    }
-                    "Array<Tile>" => {
-                        let ldtk_tile_vec: Vec<ldtk::TilesetRectangle> =
-                            serde_json::from_value(value.clone())?;
-                        let array_tile = ldtk_tile_vec.iter().map(TilesetRectangle::new).collect();
-                        FieldInstanceValue::ArrayTile(array_tile)
-                    }
-                    // TODO: finish me!
-                    _ => {
-                        return Err(FieldInstanceValueParseError::UnknownFieldInstanceType(
-                            field_instance_type.to_owned(),
-                        ))
-                    }
-                }
-            },
+            value: FieldInstanceValue::new(
+                &value.field_instance_type,
+                value.value.as_ref().unwrap_or(&serde_json::Value::Null),
+            )?,
             def_uid: value.def_uid,
         })
     }
 }
+
+impl FieldInstanceValue {
+    pub(crate) fn new(
+        field_instance_type: &str,
+        value: &serde_json::Value,
+    ) -> Result<Self, FieldInstanceValueParseError> {
+        if let Some(element_type) = field_instance_type
+            .strip_prefix("Array<")
+            .and_then(|inner| inner.strip_suffix('>'))
+        {
+            let values = match value {
+                serde_json::Value::Null => &[],
+                value => value
+                    .as_array()
+                    .ok_or(FieldInstanceValueParseError::BadArray)?
+                    .as_slice(),
+            };
+
+            return Ok(match element_type {
+                "Int" => Self::ArrayInt(parse_array(values, parse_int)?),
+                "Float" => Self::ArrayFloat(parse_array(values, parse_float)?),
+                "String" => Self::ArrayString(parse_array(values, parse_string)?),
+                "Multilines" => Self::ArrayMultilines(parse_array(values, parse_string)?),
+                "Bool" => Self::ArrayBool(parse_array(values, parse_bool)?),
+                "Color" => Self::ArrayColor(parse_array(values, parse_color)?),
+                "FilePath" => Self::ArrayFilePath(parse_array(values, parse_string)?),
+                "Tile" => Self::ArrayTile(parse_array(values, parse_tile)?),
+                "EntityRef" => Self::ArrayEntityRef(parse_array(values, parse_entity_ref)?),
+                "Point" => Self::ArrayPoint(parse_array(values, parse_point)?),
+                _ if is_enum_type(element_type) => {
+                    Self::ArrayEnum(parse_array(values, parse_string)?)
+                }
+                _ => {
+                    return Err(FieldInstanceValueParseError::UnknownFieldInstanceType(
+                        field_instance_type.to_owned(),
+                    ))
+                }
+            });
+        }
+
+        Ok(match field_instance_type {
+            "Int" => Self::Int(parse_int(value)?),
+            "Float" => Self::Float(parse_float(value)?),
+            "String" => Self::String(parse_string(value)?),
+            "Multilines" => Self::Multilines(parse_string(value)?),
+            "Bool" => Self::Bool(parse_bool(value)?),
+            "Color" => Self::Color(parse_color(value)?),
+            "FilePath" => Self::FilePath(parse_string(value)?),
+            "Tile" => Self::Tile(parse_tile(value)?),
+            "EntityRef" => Self::EntityRef(parse_entity_ref(value)?),
+            "Point" => Self::Point(parse_point(value)?),
+            _ if is_enum_type(field_instance_type) => Self::Enum(parse_string(value)?),
+            _ => {
+                return Err(FieldInstanceValueParseError::UnknownFieldInstanceType(
+                    field_instance_type.to_owned(),
+                ))
+            }
+        })
+    }
+}
+
+// Enum types are given as "LocalEnum.<identifier>" or "ExternEnum.<identifier>"
+fn is_enum_type(field_instance_type: &str) -> bool {
+    field_instance_type.starts_with("LocalEnum.") || field_instance_type.starts_with("ExternEnum.")
+}
+
+fn parse_array<T>(
+    values: &[serde_json::Value],
+    parse: impl Fn(&serde_json::Value) -> Result<T, FieldInstanceValueParseError>,
+) -> Result<Vec<T>, FieldInstanceValueParseError> {
+    values.iter().map(parse).collect()
+}
+
+fn parse_int(value: &serde_json::Value) -> Result<Option<i64>, FieldInstanceValueParseError> {
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        value.as_i64().ok_or(FieldInstanceValueParseError::BadInt)?,
+    ))
+}
+
+fn parse_float(value: &serde_json::Value) -> Result<Option<f64>, FieldInstanceValueParseError> {
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        value
+            .as_f64()
+            .ok_or(FieldInstanceValueParseError::BadFloat)?,
+    ))
+}
+
+fn parse_string(value: &serde_json::Value) -> Result<Option<String>, FieldInstanceValueParseError> {
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        value
+            .as_str()
+            .ok_or(FieldInstanceValueParseError::BadString)?
+            .to_owned(),
+    ))
+}
+
+// LDtk booleans are never nullable
+fn parse_bool(value: &serde_json::Value) -> Result<bool, FieldInstanceValueParseError> {
+    if value.is_null() {
+        return Err(FieldInstanceValueParseError::ValueIsNone);
+    }
+
+    value.as_bool().ok_or(FieldInstanceValueParseError::BadBool)
+}
+
+fn parse_color(value: &serde_json::Value) -> Result<Option<Color>, FieldInstanceValueParseError> {
+    match parse_string(value)? {
+        Some(color) => Ok(Some(bevy_color_from_ldtk(&color)?)),
+        None => Ok(None),
+    }
+}
+
+fn parse_tile(
+    value: &serde_json::Value,
+) -> Result<Option<TilesetRectangle>, FieldInstanceValueParseError> {
+    let ldtk_tile: Option<ldtk::TilesetRectangle> = serde_json::from_value(value.clone())?;
+    Ok(ldtk_tile.as_ref().map(TilesetRectangle::new))
+}
+
+fn parse_entity_ref(
+    value: &serde_json::Value,
+) -> Result<Option<ReferenceToAnEntityInstance>, FieldInstanceValueParseError> {
+    let ldtk_entity_ref: Option<ldtk::ReferenceToAnEntityInstance> =
+        serde_json::from_value(value.clone())?;
+    Ok(ldtk_entity_ref
+        .as_ref()
+        .map(ReferenceToAnEntityInstance::new))
+}
+
+fn parse_point(value: &serde_json::Value) -> Result<Option<I64Vec2>, FieldInstanceValueParseError> {
+    let ldtk_point: Option<ldtk::GridPoint> = serde_json::from_value(value.clone())?;
+    Ok(ldtk_point.map(|point| (point.cx, point.cy).into()))
+}
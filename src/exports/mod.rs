@@ -3,4 +3,5 @@ pub(crate) mod level_background_position;
 pub(crate) mod neighbors;
 pub(crate) mod reference_to_an_entity_instance;
 pub(crate) mod tile_instance;
+pub(crate) mod toc;
 pub(crate) mod world_layout;
@@ -9,7 +9,7 @@ pub enum NeighbourError {
     BadString(String),
 }
 
-#[derive(Clone, Debug, Reflect)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
 pub enum NeighbourDir {
     North,
     South,
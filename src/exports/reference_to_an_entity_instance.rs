@@ -1,8 +1,10 @@
 use bevy::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::ldtk;
 
-#[derive(Debug, Default, Reflect)]
+#[derive(Clone, Debug, Default, Deserialize, Reflect, Serialize)]
 pub struct ReferenceToAnEntityInstance {
     pub entity_iid: String,
     pub layer_iid: String,
@@ -11,7 +13,7 @@ pub struct ReferenceToAnEntityInstance {
 }
 
 impl ReferenceToAnEntityInstance {
-    pub(crate) fn _new(value: &ldtk::ReferenceToAnEntityInstance) -> Self {
+    pub(crate) fn new(value: &ldtk::ReferenceToAnEntityInstance) -> Self {
         Self {
             entity_iid: value.entity_iid.clone(),
             layer_iid: value.layer_iid.clone(),
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::exports::field_instance::FieldInstanceValue;
+use crate::exports::field_instance::FieldInstanceValueParseError;
+use crate::exports::reference_to_an_entity_instance::ReferenceToAnEntityInstance;
+use crate::ldtk;
+
+#[derive(Debug, Error)]
+pub enum TocError {
+    #[error("No field definition for toc field {0}?")]
+    BadFieldIdentifier(String),
+    #[error("Toc fields are not an object?")]
+    FieldsNotAnObject,
+    #[error(transparent)]
+    FieldInstanceValueParseError(#[from] FieldInstanceValueParseError),
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+// Entity instances of one definition with "export to table of content" enabled, from every
+// level of the project, whether loaded or not.
+#[derive(Clone, Debug, Reflect)]
+pub struct TocEntry {
+    pub identifier: String,
+    pub instances: Vec<TocInstance>,
+}
+
+#[derive(Clone, Debug, Reflect)]
+pub struct TocInstance {
+    pub iids: ReferenceToAnEntityInstance,
+    pub size: Vec2,
+    // In the Bevy coordinate system, like LevelAsset::location
+    pub world_location: Vec2,
+    // Only the fields with "export to table of content" enabled, keyed by identifier
+    #[reflect(ignore)]
+    pub fields: serde_json::Value,
+}
+
+impl TocEntry {
+    pub(crate) fn new(
+        value: &ldtk::LdtkTableOfContentEntry,
+        field_defs: &[ldtk::FieldDefinition],
+    ) -> Result<Self, TocError> {
+        let field_types = field_defs
+            .iter()
+            .map(|field_def| {
+                (
+                    field_def.identifier.as_str(),
+                    field_def.field_definition_type.as_str(),
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            identifier: value.identifier.clone(),
+            instances: value
+                .instances_data
+                .iter()
+                .map(|instance| TocInstance::new(instance, &field_types))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TocInstance {
+    // LDtk saves toc fields as raw __value JSON, so they're parsed with the field's type the
+    // same way entity field instances are.
+    pub(crate) fn new(
+        value: &ldtk::LdtkTocInstanceData,
+        field_types: &HashMap<&str, &str>,
+    ) -> Result<Self, TocError> {
+        let fields = match &value.fields {
+            None => serde_json::Map::new(),
+            Some(serde_json::Value::Object(fields)) => fields
+                .iter()
+                .map(|(identifier, value)| {
+                    let field_type = field_types
+                        .get(identifier.as_str())
+                        .ok_or_else(|| TocError::BadFieldIdentifier(identifier.clone()))?;
+                    let value = FieldInstanceValue::new(field_type, value)?;
+                    Ok((identifier.clone(), serde_json::to_value(value)?))
+                })
+                .collect::<Result<_, TocError>>()?,
+            Some(_) => return Err(TocError::FieldsNotAnObject),
+        };
+
+        Ok(Self {
+            iids: ReferenceToAnEntityInstance::new(&value.iids),
+            size: (value.wid_px as f32, value.hei_px as f32).into(),
+            world_location: (value.world_x as f32, -value.world_y as f32).into(),
+            fields: serde_json::Value::Object(fields),
+        })
+    }
+
+    // Takes the same types as FieldInstances::deserialize, with field identifiers as keys
+    pub fn deserialize_fields<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_value(self.fields.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Fields {
+        tint: Option<Color>,
+        target: Option<bevy::math::I64Vec2>,
+        hp: Option<i64>,
+    }
+
+    #[test]
+    fn fields_parse_like_field_instances() {
+        let instance: ldtk::LdtkTocInstanceData = serde_json::from_value(serde_json::json!({
+            "iids": {
+                "entityIid": "e", "layerIid": "l", "levelIid": "v", "worldIid": "w"
+            },
+            "widPx": 16, "heiPx": 16, "worldX": 0, "worldY": 0,
+            "fields": { "tint": "#ff0000", "target": { "cx": 3, "cy": 4 }, "hp": 5 }
+        }))
+        .expect("valid toc instance");
+
+        let field_types = [("tint", "Color"), ("target", "Point"), ("hp", "Int")]
+            .into_iter()
+            .collect();
+
+        let toc_instance = TocInstance::new(&instance, &field_types).expect("known fields");
+
+        assert_eq!(
+            toc_instance.deserialize_fields::<Fields>().expect("fields"),
+            Fields {
+                tint: Some(Color::rgb(1.0, 0.0, 0.0)),
+                target: Some((3, 4).into()),
+                hp: Some(5),
+            }
+        );
+    }
+}
@@ -1,4 +1,7 @@
 #[allow(clippy::enum_variant_names)]
+#[allow(clippy::doc_lazy_continuation)]
 mod ldtk_json_1_5_3;
+mod version;
 
 pub(crate) use ldtk_json_1_5_3::*;
+pub(crate) use version::LdtkVersion;
@@ -0,0 +1,360 @@
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// The generated schema is for 1.5.3. Older files are migrated into its shape before being
+// deserialized, by filling in what they lack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LdtkVersion {
+    V1_4,
+    V1_5,
+}
+
+impl LdtkVersion {
+    // None for missing, malformed or unsupported versions
+    pub(crate) fn new(json_version: &str) -> Option<Self> {
+        let mut parts = json_version.split('.').map(str::parse::<u32>);
+
+        match (parts.next()?.ok()?, parts.next()?.ok()?) {
+            (1, 4) => Some(Self::V1_4),
+            (1, 5) => Some(Self::V1_5),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn migrate_project(&self, value: &mut Value) {
+        if *self == Self::V1_5 {
+            return;
+        }
+
+        for layer_def in array_mut(value.pointer_mut("/defs/layers")) {
+            fill(layer_def, "intGridValuesGroups", json!([]));
+            fill(layer_def, "uiFilterTags", json!([]));
+            fill(layer_def, "renderInWorldView", json!(true));
+            fill(layer_def, "canSelectWhenInactive", json!(true));
+
+            for int_grid_value in array_mut(layer_def.get_mut("intGridValues")) {
+                fill(int_grid_value, "groupUid", json!(0));
+            }
+
+            for rule_group in array_mut(layer_def.get_mut("autoRuleGroups")) {
+                fill(rule_group, "biomeRequirementMode", json!(0));
+                fill(rule_group, "requiredBiomeValues", json!([]));
+                fill(rule_group, "isOptional", json!(false));
+
+                for rule in array_mut(rule_group.get_mut("rules")) {
+                    migrate_rule(rule);
+                }
+            }
+        }
+
+        for entity_def in array_mut(value.pointer_mut("/defs/entities")) {
+            fill(entity_def, "allowOutOfBounds", json!(false));
+            fill(entity_def, "exportToToc", json!(false));
+
+            for field_def in array_mut(entity_def.get_mut("fieldDefs")) {
+                migrate_field_def(field_def);
+            }
+        }
+
+        for field_def in array_mut(value.pointer_mut("/defs/levelFields")) {
+            migrate_field_def(field_def);
+        }
+
+        for level in array_mut(value.get_mut("levels")) {
+            self.migrate_level(level);
+        }
+
+        for world in array_mut(value.get_mut("worlds")) {
+            for level in array_mut(world.get_mut("levels")) {
+                self.migrate_level(level);
+            }
+        }
+
+        if let Some(object) = value.as_object_mut() {
+            object.entry("toc").or_insert(json!([]));
+            object.entry("customCommands").or_insert(json!([]));
+        }
+
+        // Before instancesData, the toc only listed references. Entities of external levels
+        // aren't in the project file, so only their iids are known.
+        let entity_placements = entity_placements(value);
+
+        for entry in array_mut(value.get_mut("toc")) {
+            let instances_data = entry
+                .get("instances")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|iids| {
+                    let [world_x, world_y, wid_px, hei_px] = iids
+                        .get("entityIid")
+                        .and_then(Value::as_str)
+                        .and_then(|iid| entity_placements.get(iid))
+                        .copied()
+                        .unwrap_or_default();
+                    json!({
+                        "iids": iids,
+                        "widPx": wid_px,
+                        "heiPx": hei_px,
+                        "worldX": world_x,
+                        "worldY": world_y,
+                        "fields": {},
+                    })
+                })
+                .collect();
+            fill(entry, "instancesData", Value::Array(instances_data));
+        }
+    }
+
+    // For levels in the project file, and for .ldtkl files, which carry the app version in
+    // their header instead of a JSON version.
+    pub(crate) fn migrate_level(&self, value: &mut Value) {
+        if *self == Self::V1_5 {
+            return;
+        }
+
+        for layer_instance in array_mut(value.get_mut("layerInstances")) {
+            for key in ["gridTiles", "autoLayerTiles"] {
+                for tile in array_mut(layer_instance.get_mut(key)) {
+                    fill(tile, "a", json!(1.0));
+                }
+            }
+        }
+    }
+}
+
+// World location and size of every entity instance in the project file, by iid
+fn entity_placements(value: &Value) -> HashMap<String, [i64; 4]> {
+    let levels = value["levels"].as_array().into_iter().flatten().chain(
+        value["worlds"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|world| world["levels"].as_array().into_iter().flatten()),
+    );
+
+    levels
+        .flat_map(|level| level["layerInstances"].as_array().into_iter().flatten())
+        .flat_map(|layer| layer["entityInstances"].as_array().into_iter().flatten())
+        .filter_map(|entity| {
+            let placement = ["__worldX", "__worldY", "width", "height"]
+                .map(|key| entity[key].as_i64().unwrap_or_default());
+            Some((entity["iid"].as_str()?.to_owned(), placement))
+        })
+        .collect()
+}
+
+fn migrate_field_def(field_def: &mut Value) {
+    fill(field_def, "exportToToc", json!(false));
+    fill(field_def, "searchable", json!(false));
+    fill(field_def, "allowOutOfLevelRef", json!(true));
+}
+
+// Rules used to pick from tile ids, rather than from rectangles of them. Single rules picked
+// one of the ids, Stamp rules placed all of them as one stamp.
+fn migrate_rule(rule: &mut Value) {
+    if rule.get("tileRectsIds").is_none() {
+        let tile_ids = rule
+            .get("tileIds")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let tile_rects_ids = match rule.get("tileMode").and_then(Value::as_str) {
+            Some("Stamp") => vec![Value::Array(tile_ids)],
+            _ => tile_ids
+                .into_iter()
+                .map(|tile_id| json!([tile_id]))
+                .collect(),
+        };
+        fill(rule, "tileRectsIds", Value::Array(tile_rects_ids));
+    }
+
+    fill(rule, "alpha", json!(1.0));
+    fill(rule, "invalidated", json!(false));
+    fill(rule, "tileRandomXMin", json!(0));
+    fill(rule, "tileRandomXMax", json!(0));
+    fill(rule, "tileRandomYMin", json!(0));
+    fill(rule, "tileRandomYMax", json!(0));
+    fill(rule, "tileXOffset", json!(0));
+    fill(rule, "tileYOffset", json!(0));
+}
+
+fn fill(value: &mut Value, key: &str, default: Value) {
+    if let Some(object) = value.as_object_mut() {
+        object.entry(key).or_insert(default);
+    }
+}
+
+fn array_mut(value: Option<&mut Value>) -> impl Iterator<Item = &mut Value> {
+    value
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flat_map(|array| array.iter_mut())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ldtk::LdtkJson;
+
+    fn load(bytes: &str) -> LdtkJson {
+        let mut value: Value = serde_json::from_str(bytes).expect("valid json");
+        let version = value["jsonVersion"].as_str().expect("a version").to_owned();
+        LdtkVersion::new(&version)
+            .expect("a supported version")
+            .migrate_project(&mut value);
+        serde_json::from_value(value).expect("a valid project")
+    }
+
+    #[test]
+    fn detects_versions() {
+        assert_eq!(LdtkVersion::new("1.4.3"), Some(LdtkVersion::V1_4));
+        assert_eq!(LdtkVersion::new("1.5.3"), Some(LdtkVersion::V1_5));
+        assert_eq!(LdtkVersion::new("1.3.4"), None);
+        assert_eq!(LdtkVersion::new("2.0.0"), None);
+        assert_eq!(LdtkVersion::new("garbage"), None);
+    }
+
+    #[test]
+    fn migrates_rule_tiles() {
+        let mut single = json!({ "tileMode": "Single", "tileIds": [1, 2, 3] });
+        let mut stamp = json!({ "tileMode": "Stamp", "tileIds": [1, 2, 3] });
+
+        migrate_rule(&mut single);
+        migrate_rule(&mut stamp);
+
+        assert_eq!(single["tileRectsIds"], json!([[1], [2], [3]]));
+        assert_eq!(stamp["tileRectsIds"], json!([[1, 2, 3]]));
+    }
+
+    #[test]
+    fn migrates_level_tiles() {
+        let tile = json!({ "d": [0], "f": 0, "px": [0, 0], "src": [0, 0], "t": 0 });
+        let mut level =
+            json!({ "layerInstances": [{ "gridTiles": [tile], "autoLayerTiles": [] }] });
+
+        LdtkVersion::V1_4.migrate_level(&mut level);
+
+        assert_eq!(
+            level.pointer("/layerInstances/0/gridTiles/0/a"),
+            Some(&json!(1.0))
+        );
+    }
+
+    // Not saved by LDtk: ldtk_1_5_3.ldtk with every 1.5 addition taken out by hand. It only
+    // checks that migrating reverses that, see loads_saved_1_4_project for real 1.4 files.
+    #[test]
+    fn loads_1_4_project() {
+        let project = load(include_str!(
+            "../../tests/fixtures/ldtk_1_4_3_downgraded.ldtk"
+        ));
+        let expected = load(include_str!("../../tests/fixtures/ldtk_1_5_3.ldtk"));
+
+        assert_eq!(project.json_version, "1.4.3");
+        assert_eq!(project.levels, expected.levels);
+        assert!(project.defs.layers.iter().all(|layer_def| {
+            layer_def.int_grid_values_groups.is_empty()
+                && layer_def
+                    .auto_rule_groups
+                    .iter()
+                    .flat_map(|group| group.rules.iter())
+                    .all(|rule| !rule.tile_rects_ids.is_empty())
+        }));
+        assert_eq!(
+            project.toc[0].instances_data,
+            expected.toc[0].instances_data
+        );
+    }
+
+    // Needs a project with external levels, saved by LDtk 1.4.x itself, which the fixtures lack
+    // so far. Run with --ignored once it is added.
+    #[test]
+    #[ignore = "needs tests/fixtures/ldtk_1_4_x/project.ldtk, saved by LDtk 1.4.x"]
+    fn loads_saved_1_4_project() {
+        let dir =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ldtk_1_4_x");
+        let read = |path: &std::path::Path| -> Value {
+            serde_json::from_slice(&std::fs::read(path).expect("a saved fixture"))
+                .expect("valid json")
+        };
+
+        let raw = read(&dir.join("project.ldtk"));
+        assert!(raw["jsonVersion"].as_str().unwrap().starts_with("1.4."));
+        assert_eq!(raw["externalLevels"], json!(true));
+
+        let mut value = raw.clone();
+        LdtkVersion::new(raw["jsonVersion"].as_str().unwrap())
+            .unwrap()
+            .migrate_project(&mut value);
+        let project: LdtkJson = serde_json::from_value(value).expect("a valid project");
+
+        // Rules keep the tiles LDtk saved, stamps as a single rect
+        let raw_rules = raw["defs"]["layers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|layer_def| layer_def["autoRuleGroups"].as_array().unwrap())
+            .flat_map(|group| group["rules"].as_array().unwrap());
+        let rules = project
+            .defs
+            .layers
+            .iter()
+            .flat_map(|layer_def| &layer_def.auto_rule_groups)
+            .flat_map(|group| &group.rules);
+        for (raw_rule, rule) in raw_rules.zip(rules) {
+            let tile_ids = serde_json::from_value::<Vec<i64>>(raw_rule["tileIds"].clone()).unwrap();
+            match raw_rule["tileMode"].as_str().unwrap() {
+                "Stamp" => assert_eq!(rule.tile_rects_ids, vec![tile_ids]),
+                _ => assert_eq!(rule.tile_rects_ids.concat(), tile_ids),
+            }
+        }
+
+        for (raw_entry, entry) in raw["toc"].as_array().unwrap().iter().zip(&project.toc) {
+            let iids = entry
+                .instances_data
+                .iter()
+                .map(|instance_data| json!(instance_data.iids.entity_iid))
+                .collect::<Vec<_>>();
+            let raw_iids = raw_entry["instances"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|instance| instance["entityIid"].clone())
+                .collect::<Vec<_>>();
+            assert_eq!(iids, raw_iids);
+        }
+
+        let level_paths = project
+            .levels
+            .iter()
+            .chain(project.worlds.iter().flat_map(|world| &world.levels))
+            .filter_map(|level| level.external_rel_path.clone())
+            .collect::<Vec<_>>();
+        assert!(!level_paths.is_empty());
+
+        for level_path in level_paths {
+            let mut value = read(&dir.join(level_path));
+            let app_version = value["__header__"]["appVersion"]
+                .as_str()
+                .unwrap()
+                .to_owned();
+            assert!(app_version.starts_with("1.4."));
+
+            LdtkVersion::new(&app_version)
+                .unwrap()
+                .migrate_level(&mut value);
+            let level: crate::ldtk::Level = serde_json::from_value(value).expect("a valid level");
+            assert!(level.layer_instances.is_some());
+        }
+    }
+
+    #[test]
+    fn loads_1_5_project() {
+        let project = load(include_str!("../../tests/fixtures/ldtk_1_5_3.ldtk"));
+
+        assert_eq!(project.json_version, "1.5.3");
+        assert_eq!(project.levels.len(), 1);
+        assert_eq!(project.toc[0].instances_data.len(), 1);
+    }
+}
@@ -1,18 +1,80 @@
+#[cfg(feature = "runtime")]
+mod app_ex;
+// Code generation for build scripts, which don't need the rest of the crate:
+// covey_of_worlds = { default-features = false, features = ["codegen"] }
+// Also built for tests, so that a plain cargo test covers it.
+#[cfg(any(feature = "codegen", test))]
+pub mod codegen;
+#[cfg(feature = "runtime")]
+mod commands_ex;
+#[cfg(feature = "runtime")]
 mod defs;
+#[cfg(feature = "runtime")]
 mod exports;
 mod ldtk;
+#[cfg(feature = "runtime")]
 mod plugin;
+#[cfg(feature = "runtime")]
+mod project_export;
+#[cfg(feature = "runtime")]
 mod system_params;
+#[cfg(feature = "runtime")]
 mod util;
 
+#[cfg(feature = "runtime")]
 pub mod prelude {
+    pub use crate::app_ex::LdtkAppEx;
+    pub use crate::app_ex::LdtkEntity;
     pub use crate::assets::entity::EntityAsset;
+    pub use crate::assets::layer::LayerAsset;
+    pub use crate::assets::layer::LayerType;
+    pub use crate::assets::level::LevelAsset;
     pub use crate::assets::project::ProjectAsset;
+    pub use crate::assets::world::WorldAsset;
+    pub use crate::commands_ex::LdtkCommandsEx;
+    pub use crate::components::entity_refs::EntityRef;
+    pub use crate::components::entity_refs::EntityRefs;
+    pub use crate::components::field_instances::FieldInstances;
+    pub use crate::components::iid::Iid;
+    pub use crate::components::iid::IidMap;
+    pub use crate::components::int_grid::IntGrid;
+    pub use crate::components::int_grid_colliders::IntGridColliderFilter;
+    pub use crate::components::int_grid_colliders::IntGridColliderShapes;
+    pub use crate::components::int_grid_colliders::IntGridColliders;
+    pub use crate::components::int_grid_colliders::IntGridRuleColliders;
+    pub use crate::components::level_selection::LevelSelection;
+    pub use crate::components::level_selection::LevelSelectionAnchor;
+    pub use crate::components::level_tracker::LevelTracker;
+    pub use crate::components::level_tracker::LevelTransitionEvent;
+    pub use crate::components::parallax::Parallax;
+    pub use crate::components::parallax::ParallaxCamera;
+    pub use crate::components::snapshot::LdtkEntitySnapshot;
+    pub use crate::components::snapshot::LdtkSnapshot;
+    pub use crate::defs::entity_definition::EntityDefinition;
+    pub use crate::exports::field_instance::FieldInstance;
+    pub use crate::exports::field_instance::FieldInstanceValue;
+    pub use crate::exports::neighbors::NeighbourDir;
+    pub use crate::exports::toc::TocEntry;
+    pub use crate::exports::toc::TocInstance;
     pub use crate::plugin::CoveyOfWorldsPlugin;
+    pub use crate::plugin::EntityTransformReload;
+    pub use crate::plugin::TileLayerRendering;
+    pub use crate::project_export::export_ldtk_project;
+    pub use crate::project_export::LdtkProjectExport;
+    pub use crate::system_params::entity::LdtkEntityCommands;
+    pub use crate::system_params::entity::LdtkEntityCommandsEx;
+    pub use crate::system_params::layer::LdtkLayerCommands;
+    pub use crate::system_params::layer::LdtkLayerCommandsEx;
+    pub use crate::system_params::level::LdtkLevelCommands;
+    pub use crate::system_params::level::LdtkLevelCommandsEx;
     pub use crate::system_params::project::LdtkProjectCommands;
     pub use crate::system_params::project::LdtkProjectCommandsEntityEx;
     pub use crate::system_params::project::LdtkProjectCommandsEx;
+    pub use crate::system_params::world::LdtkWorldCommands;
+    pub use crate::system_params::world::LdtkWorldCommandsEx;
 }
 
+#[cfg(feature = "runtime")]
 mod assets;
+#[cfg(feature = "runtime")]
 mod components;
@@ -1,32 +1,113 @@
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
 use bevy::utils::error;
 use bevy::utils::HashSet;
 
 use crate::assets::entity::EntityAsset;
 use crate::assets::layer::LayerAsset;
 use crate::assets::level::LevelAsset;
+use crate::assets::level_asset_loader::LevelAssetLoader;
 use crate::assets::project::ProjectAsset;
 use crate::assets::project_asset_loader::ProjectAssetLoader;
 use crate::assets::traits::LdtkAsset;
 use crate::assets::traits::LdtkAssetChildLoader;
 use crate::assets::traits::LdtkAssetLoadEvent;
 use crate::assets::world::WorldAsset;
+use crate::components::auto_layer::AutoLayerCache;
+use crate::components::entity_refs::EntityRefs;
+use crate::components::field_instances::FieldInstances;
 use crate::components::iid::Iid;
+use crate::components::iid::IidMap;
+use crate::components::int_grid::IntGrid;
+use crate::components::int_grid_colliders::IntGridColliderRules;
+use crate::components::int_grid_colliders::IntGridColliders;
+use crate::components::int_grid_colliders::IntGridRuleColliders;
+use crate::components::level_selection::LevelSelection;
+use crate::components::level_selection::LevelSelectionAnchor;
+use crate::components::level_tracker::LevelTracker;
+use crate::components::level_tracker::LevelTransitionEvent;
+use crate::components::parallax::Parallax;
+use crate::components::parallax::ParallaxCamera;
+use crate::components::snapshot::LdtkSnapshot;
+use crate::components::snapshot::LdtkSnapshotComponents;
 use crate::components::tiles::Tiles;
 use crate::components::tileset_rectangle::TilesetRectangle;
 use crate::components::traits::LdtkComponent;
 
-pub struct CoveyOfWorldsPlugin;
+// How tile layers are drawn.
+//   Composited: every tile is drawn into a new image on the CPU, shown on a single quad.
+//   Mesh: a single mesh per layer, with one quad per tile sampling the tileset directly.
+// Editing Tiles at runtime only redraws the touched regions of a composited image, and only
+// rewrites the quads of the touched locations of a layer mesh.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Resource)]
+pub enum TileLayerRendering {
+    #[default]
+    Composited,
+    Mesh,
+}
+
+// What happens to an LDtk entity's Transform when its asset is reloaded.
+//   Always: the translation is reset to the one from LDtk.
+//   IfUnchanged: only entities that haven't moved since LDtk last placed them are moved.
+//   Never: the translation is only set on spawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Resource)]
+pub enum EntityTransformReload {
+    Always,
+    #[default]
+    IfUnchanged,
+    Never,
+}
+
+#[derive(Default)]
+pub struct CoveyOfWorldsPlugin {
+    pub tile_layer_rendering: TileLayerRendering,
+    pub entity_transform_reload: EntityTransformReload,
+    // Keeps the parsed LDtk JSON of every project and external level for as long as they're
+    // loaded, which export_ldtk_project needs. Off by default to save the memory.
+    pub keep_ldtk_source: bool,
+}
 
 impl Plugin for CoveyOfWorldsPlugin {
     fn build(&self, app: &mut App) {
         app //
+            .insert_resource(self.tile_layer_rendering)
+            .insert_resource(self.entity_transform_reload)
+            .register_type::<TileLayerRendering>()
+            .register_type::<EntityTransformReload>();
+
+        app //
+            .register_type::<EntityRefs>()
+            .register_type::<FieldInstances>()
             .register_type::<Iid>()
+            .register_type::<IntGrid>()
+            .register_type::<IntGridColliders>()
+            .register_type::<IntGridColliderRules>()
+            .register_type::<IntGridRuleColliders>()
+            .register_type::<LevelSelection>()
+            .register_type::<LevelSelectionAnchor>()
+            .register_type::<LevelTracker>()
+            .register_type::<Parallax>()
+            .register_type::<ParallaxCamera>()
             .register_type::<Tiles>();
 
+        app //
+            .init_resource::<IidMap>()
+            .add_systems(Update, IidMap::iid_map_system);
+
+        app //
+            .init_resource::<LdtkSnapshot>()
+            .init_resource::<LdtkSnapshotComponents>()
+            .add_systems(
+                Update,
+                LdtkSnapshot::snapshot_despawn_system.before(IidMap::iid_map_system),
+            )
+            .add_systems(PostUpdate, LdtkSnapshot::snapshot_restore_system);
+
         app //
             .init_asset::<ProjectAsset>()
-            .init_asset_loader::<ProjectAssetLoader>()
+            .register_asset_loader(ProjectAssetLoader {
+                keep_source: self.keep_ldtk_source,
+            })
             .add_event::<LdtkAssetLoadEvent<ProjectAsset>>()
             .register_asset_reflect::<ProjectAsset>()
             .register_type::<HashSet<Handle<ProjectAsset>>>()
@@ -38,6 +119,7 @@ impl Plugin for CoveyOfWorldsPlugin {
                     <Transform as LdtkComponent<ProjectAsset>>::ldtk_asset_event.map(error),
                     ProjectAsset::on_create_system,
                     ProjectAsset::on_modified_system,
+                    ProjectAsset::handle_removed_system,
                     ProjectAsset::load_children_system.map(error),
                 ),
             );
@@ -54,12 +136,22 @@ impl Plugin for CoveyOfWorldsPlugin {
                     <Transform as LdtkComponent<WorldAsset>>::ldtk_asset_event.map(error),
                     WorldAsset::on_create_system,
                     WorldAsset::on_modified_system,
-                    WorldAsset::load_children_system.map(error),
                 ),
             );
 
+        app //
+            .init_resource::<LevelSelection>()
+            .add_systems(Update, LevelSelection::level_selection_system.map(error));
+
+        app //
+            .add_event::<LevelTransitionEvent>()
+            .add_systems(Update, LevelTracker::level_tracker_system);
+
         app //
             .init_asset::<LevelAsset>()
+            .register_asset_loader(LevelAssetLoader {
+                keep_source: self.keep_ldtk_source,
+            })
             .add_event::<LdtkAssetLoadEvent<LevelAsset>>()
             .register_asset_reflect::<LevelAsset>()
             .add_systems(
@@ -68,6 +160,7 @@ impl Plugin for CoveyOfWorldsPlugin {
                     <Name as LdtkComponent<LevelAsset>>::ldtk_asset_event.map(error),
                     <Iid as LdtkComponent<LevelAsset>>::ldtk_asset_event.map(error),
                     <Transform as LdtkComponent<LevelAsset>>::ldtk_asset_event.map(error),
+                    <FieldInstances as LdtkComponent<LevelAsset>>::ldtk_asset_event.map(error),
                     LevelAsset::on_create_system,
                     LevelAsset::on_modified_system,
                     LevelAsset::load_children_system.map(error),
@@ -90,7 +183,25 @@ impl Plugin for CoveyOfWorldsPlugin {
                     LayerAsset::on_modified_system,
                     LayerAsset::load_children_system.map(error),
                     LayerAsset::layer_tiles_system.map(error),
+                    LayerAsset::int_grid_system.map(error),
+                    LayerAsset::layer_parallax_system.map(error),
                 ),
+            )
+            .add_systems(
+                PostUpdate,
+                Parallax::parallax_system
+                    .map(error)
+                    .before(TransformSystem::TransformPropagate),
+            );
+
+        app //
+            .add_systems(Update, AutoLayerCache::auto_layer_system);
+
+        app //
+            .init_resource::<IntGridColliderRules>()
+            .add_systems(
+                Update,
+                IntGridColliders::int_grid_colliders_system.map(error),
             );
 
         app //
@@ -102,11 +213,14 @@ impl Plugin for CoveyOfWorldsPlugin {
                 (
                     <Name as LdtkComponent<EntityAsset>>::ldtk_asset_event.map(error),
                     <Iid as LdtkComponent<EntityAsset>>::ldtk_asset_event.map(error),
-                    <Transform as LdtkComponent<EntityAsset>>::ldtk_asset_event.map(error),
                     <TilesetRectangle as LdtkComponent<EntityAsset>>::ldtk_asset_event.map(error),
+                    <FieldInstances as LdtkComponent<EntityAsset>>::ldtk_asset_event.map(error),
+                    <EntityRefs as LdtkComponent<EntityAsset>>::ldtk_asset_event.map(error),
                     EntityAsset::on_create_system,
                     EntityAsset::on_modified_system,
                     EntityAsset::entity_tile_system.map(error),
+                    EntityAsset::entity_transform_system.map(error),
+                    EntityRefs::entity_refs_system.after(IidMap::iid_map_system),
                 ),
             );
     }
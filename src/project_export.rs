@@ -0,0 +1,640 @@
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::assets::layer::LayerAsset;
+use crate::assets::layer::LayerType;
+use crate::assets::level::LevelAsset;
+use crate::assets::project::ProjectAsset;
+use crate::components::auto_layer::AutoLayerCache;
+use crate::components::auto_layer::AutoLayerRules;
+use crate::components::field_instances::FieldInstances;
+use crate::components::iid::IidMap;
+use crate::components::int_grid::IntGrid;
+use crate::components::snapshot::LdtkSnapshot;
+use crate::components::tiles::Tiles;
+use crate::exports::field_instance::FieldInstance;
+use crate::exports::field_instance::FieldInstanceValueParseError;
+use crate::exports::tile_instance::TileInstance;
+use crate::ldtk;
+
+#[derive(Debug, Error)]
+pub enum LdtkProjectExportError {
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    FieldInstanceValueParseError(#[from] FieldInstanceValueParseError),
+    #[error("No LDtk project with iid! {0}")]
+    BadProjectIid(String),
+    #[error("Bad handle?")]
+    BadHandle,
+    #[error("Project was loaded without CoveyOfWorldsPlugin::keep_ldtk_source!")]
+    MissingSource,
+}
+
+// The project file, and the .ldtkl files of its loaded external levels keyed by their path
+// relative to the project file. External levels that were never loaded are left out, as
+// their files on disk are still current.
+#[derive(Clone, Debug, Default)]
+pub struct LdtkProjectExport {
+    pub project: String,
+    pub external_levels: HashMap<String, String>,
+}
+
+impl LdtkProjectExport {
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, &self.project)?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for (external_rel_path, level) in &self.external_levels {
+            let level_path = dir.join(external_rel_path);
+            if let Some(level_dir) = level_path.parent() {
+                std::fs::create_dir_all(level_dir)?;
+            }
+            std::fs::write(level_path, level)?;
+        }
+
+        Ok(())
+    }
+}
+
+// The project as it was loaded, with the IntGrid values, tiles, entity positions and field
+// values of whatever is spawned now. LDtk entities despawned by gameplay, as recorded in
+// LdtkSnapshot::despawned, are removed. Ones that just aren't spawned are kept as they were.
+// The table of contents follows both. Needs CoveyOfWorldsPlugin::keep_ldtk_source.
+pub fn export_ldtk_project(
+    world: &World,
+    iid: &str,
+) -> Result<LdtkProjectExport, LdtkProjectExportError> {
+    let iid_map = world.resource::<IidMap>();
+
+    let project_asset = iid_map
+        .handle::<ProjectAsset>(iid)
+        .ok_or_else(|| LdtkProjectExportError::BadProjectIid(iid.to_owned()))?;
+    let project_asset = world
+        .resource::<Assets<ProjectAsset>>()
+        .get(project_asset)
+        .ok_or(LdtkProjectExportError::BadHandle)?;
+
+    let mut value = project_asset
+        .source
+        .clone()
+        .ok_or(LdtkProjectExportError::MissingSource)?;
+
+    // Older projects were migrated when loaded
+    value.json_version = "1.5.3".to_owned();
+
+    let mut external_levels = HashMap::default();
+
+    for level in value.levels.iter_mut().chain(
+        value
+            .worlds
+            .iter_mut()
+            .flat_map(|world| world.levels.iter_mut()),
+    ) {
+        let Some(external_rel_path) = level.external_rel_path.clone() else {
+            patch_level(world, project_asset, iid_map, level)?;
+            continue;
+        };
+
+        let Some(mut external_level) = iid_map
+            .entity(&level.iid)
+            .and_then(|entity| world.get::<Handle<LevelAsset>>(entity))
+            .and_then(|handle| world.resource::<Assets<LevelAsset>>().get(handle))
+            .and_then(|level_asset| level_asset.source.clone())
+        else {
+            continue;
+        };
+
+        patch_level(world, project_asset, iid_map, &mut external_level)?;
+
+        // Level fields are in both files
+        level
+            .field_instances
+            .clone_from(&external_level.field_instances);
+
+        external_levels.insert(external_rel_path, external_level);
+    }
+
+    patch_toc(world, &mut value, &external_levels);
+
+    Ok(LdtkProjectExport {
+        project: serde_json::to_string_pretty(&value)?,
+        external_levels: external_levels
+            .iter()
+            .map(|(external_rel_path, level)| {
+                Ok((
+                    external_rel_path.clone(),
+                    serde_json::to_string_pretty(level)?,
+                ))
+            })
+            .collect::<Result<_, serde_json::Error>>()?,
+    })
+}
+
+fn patch_level(
+    world: &World,
+    project_asset: &ProjectAsset,
+    iid_map: &IidMap,
+    level: &mut ldtk::Level,
+) -> Result<(), LdtkProjectExportError> {
+    let Some(entity) = iid_map.entity(&level.iid) else {
+        return Ok(());
+    };
+
+    if let Some(field_instances) = world.get::<FieldInstances>(entity) {
+        patch_field_instances(&mut level.field_instances, field_instances)?;
+    }
+
+    // Biome values are read from the level fields as loaded, like auto_layer_system does
+    let level_field_instances = world
+        .get::<Handle<LevelAsset>>(entity)
+        .and_then(|handle| world.resource::<Assets<LevelAsset>>().get(handle))
+        .map(|level_asset| level_asset.field_instances.as_slice())
+        .unwrap_or_default();
+
+    // IntGrids by layer definition uid, with the values LDtk placed auto-layer tiles for
+    let int_grids = level
+        .layer_instances
+        .iter()
+        .flatten()
+        .filter_map(|layer_instance| {
+            let int_grid = iid_map
+                .entity(&layer_instance.iid)
+                .and_then(|entity| world.get::<IntGrid>(entity))?;
+            Some((
+                layer_instance.layer_def_uid,
+                (int_grid, layer_instance.int_grid_csv.clone()),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+
+    for layer_instance in level.layer_instances.iter_mut().flatten() {
+        patch_layer_instance(
+            world,
+            project_asset,
+            iid_map,
+            &int_grids,
+            level_field_instances,
+            layer_instance,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn patch_layer_instance(
+    world: &World,
+    project_asset: &ProjectAsset,
+    iid_map: &IidMap,
+    int_grids: &HashMap<i64, (&IntGrid, Vec<i64>)>,
+    level_field_instances: &[FieldInstance],
+    layer_instance: &mut ldtk::LayerInstance,
+) -> Result<(), LdtkProjectExportError> {
+    let Some(entity) = iid_map.entity(&layer_instance.iid) else {
+        return Ok(());
+    };
+
+    if let Some(int_grid) = world.get::<IntGrid>(entity) {
+        layer_instance.int_grid_csv.clone_from(&int_grid.values);
+    }
+
+    let grid_size = layer_instance.grid_size;
+    let c_wid = layer_instance.c_wid;
+
+    // Tiles layers keep their tiles in a Tiles component, which is removed when empty
+    if layer_instance.layer_instance_type == "Tiles" {
+        layer_instance.grid_tiles = world
+            .get::<Tiles>(entity)
+            .map(|tiles| {
+                tiles
+                    .tiles
+                    .iter()
+                    .map(|tile| {
+                        let coord_id =
+                            tile.location.x / grid_size + tile.location.y / grid_size * c_wid;
+                        ldtk_tile_instance(tile, vec![coord_id])
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    // Caches are only kept once auto_layer_system has seen the IntGrid the rules read change.
+    // IntGrids edited before it ran, or inserted rather than changed, are evaluated here.
+    let evaluated = match world.get::<AutoLayerCache>(entity) {
+        Some(_) => None,
+        None => evaluate_auto_layer(
+            world,
+            project_asset,
+            int_grids,
+            level_field_instances,
+            entity,
+            layer_instance,
+        ),
+    };
+
+    if let Some(auto_layer_cache) = world.get::<AutoLayerCache>(entity).or(evaluated.as_ref()) {
+        layer_instance.auto_layer_tiles = auto_layer_cache
+            .iter()
+            .map(|(rule_uid, coord_id, tile)| ldtk_tile_instance(tile, vec![rule_uid, coord_id]))
+            .collect();
+    }
+
+    if let Some(snapshot) = world.get_resource::<LdtkSnapshot>() {
+        layer_instance
+            .entity_instances
+            .retain(|entity_instance| !snapshot.despawned.contains(&entity_instance.iid));
+    }
+
+    for entity_instance in &mut layer_instance.entity_instances {
+        patch_entity_instance(world, iid_map, grid_size, entity_instance)?;
+    }
+
+    Ok(())
+}
+
+// None when the layer has no rules, or LDtk's tiles are still current
+fn evaluate_auto_layer(
+    world: &World,
+    project_asset: &ProjectAsset,
+    int_grids: &HashMap<i64, (&IntGrid, Vec<i64>)>,
+    level_field_instances: &[FieldInstance],
+    entity: Entity,
+    layer_instance: &ldtk::LayerInstance,
+) -> Option<AutoLayerCache> {
+    let layer_definition = project_asset
+        .layer_defs
+        .get(&layer_instance.layer_def_uid)?;
+
+    let source_def_uid = match layer_definition.layer_definition_type {
+        LayerType::IntGrid => layer_instance.layer_def_uid,
+        LayerType::Autolayer => layer_definition.auto_source_layer_def_uid?,
+        _ => return None,
+    };
+
+    let (int_grid, exported_values) = int_grids.get(&source_def_uid)?;
+
+    if layer_definition.auto_rule_groups.is_empty() || int_grid.values == *exported_values {
+        return None;
+    }
+
+    let layer_asset = world
+        .get::<Handle<LayerAsset>>(entity)
+        .and_then(|handle| world.resource::<Assets<LayerAsset>>().get(handle))?;
+
+    let tileset_definition = layer_asset
+        .override_tileset_uid
+        .or(layer_asset.tileset_def_uid)
+        .and_then(|tileset_uid| project_asset.tileset_defs.get(&tileset_uid))?;
+
+    let rules = AutoLayerRules::new(
+        layer_definition,
+        layer_asset,
+        level_field_instances,
+        tileset_definition,
+    );
+
+    Some(AutoLayerCache::new(&rules, int_grid))
+}
+
+fn patch_entity_instance(
+    world: &World,
+    iid_map: &IidMap,
+    grid_size: i64,
+    entity_instance: &mut ldtk::EntityInstance,
+) -> Result<(), LdtkProjectExportError> {
+    let Some(entity) = iid_map.entity(&entity_instance.iid) else {
+        return Ok(());
+    };
+
+    if let Some(transform) = world.get::<Transform>(entity) {
+        let px = I64Vec2::new(
+            transform.translation.x.round() as i64,
+            (-transform.translation.y).round() as i64,
+        );
+        let delta = px - I64Vec2::new(entity_instance.px[0], entity_instance.px[1]);
+
+        if delta != I64Vec2::ZERO {
+            entity_instance.px = vec![px.x, px.y];
+            entity_instance.grid = vec![px.x.div_euclid(grid_size), px.y.div_euclid(grid_size)];
+            entity_instance.world_x = entity_instance.world_x.map(|world_x| world_x + delta.x);
+            entity_instance.world_y = entity_instance.world_y.map(|world_y| world_y + delta.y);
+        }
+    }
+
+    if let Some(field_instances) = world.get::<FieldInstances>(entity) {
+        patch_field_instances(&mut entity_instance.field_instances, field_instances)?;
+    }
+
+    Ok(())
+}
+
+fn patch_toc(
+    world: &World,
+    value: &mut ldtk::LdtkJson,
+    external_levels: &HashMap<String, ldtk::Level>,
+) {
+    // Entity positions once patched, from every level that was exported
+    let world_positions = value
+        .levels
+        .iter()
+        .chain(value.worlds.iter().flat_map(|world| world.levels.iter()))
+        .chain(external_levels.values())
+        .flat_map(|level| level.layer_instances.iter().flatten())
+        .flat_map(|layer_instance| &layer_instance.entity_instances)
+        .filter_map(|entity_instance| {
+            Some((
+                entity_instance.iid.clone(),
+                I64Vec2::new(entity_instance.world_x?, entity_instance.world_y?),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let despawned = world
+        .get_resource::<LdtkSnapshot>()
+        .map(|snapshot| &snapshot.despawned);
+
+    for toc_entry in &mut value.toc {
+        if let Some(despawned) = despawned {
+            toc_entry
+                .instances_data
+                .retain(|instance_data| !despawned.contains(&instance_data.iids.entity_iid));
+
+            if let Some(instances) = &mut toc_entry.instances {
+                instances.retain(|instance| !despawned.contains(&instance.entity_iid));
+            }
+        }
+
+        for instance_data in &mut toc_entry.instances_data {
+            if let Some(world_position) = world_positions.get(&instance_data.iids.entity_iid) {
+                instance_data.world_x = world_position.x;
+                instance_data.world_y = world_position.y;
+            }
+        }
+    }
+}
+
+// Only fields whose values have changed are rewritten, so untouched fields keep whatever
+// LDtk wrote for them.
+fn patch_field_instances(
+    ldtk_field_instances: &mut [ldtk::FieldInstance],
+    field_instances: &FieldInstances,
+) -> Result<(), LdtkProjectExportError> {
+    for ldtk_field_instance in ldtk_field_instances {
+        let Some(field_instance) = field_instances
+            .field_instances
+            .iter()
+            .find(|field_instance| field_instance.def_uid == ldtk_field_instance.def_uid)
+        else {
+            continue;
+        };
+
+        let loaded = FieldInstance::new(ldtk_field_instance)?;
+        if serde_json::to_value(&loaded.value)? == serde_json::to_value(&field_instance.value)? {
+            continue;
+        }
+
+        let (value, real_editor_values) = field_instance.value.to_ldtk();
+        ldtk_field_instance.value = Some(value);
+        ldtk_field_instance.real_editor_values = real_editor_values;
+    }
+
+    Ok(())
+}
+
+fn ldtk_tile_instance(tile: &TileInstance, d: Vec<i64>) -> ldtk::TileInstance {
+    ldtk::TileInstance {
+        a: tile.alpha as f64,
+        d,
+        f: tile.flip_h as i64 | (tile.flip_v as i64) << 1,
+        px: vec![tile.location.x, tile.location.y],
+        src: vec![tile.source.x as i64, tile.source.y as i64],
+        t: tile.tileset_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::UVec2;
+
+    use crate::components::iid::Iid;
+    use crate::defs::layer_definition::LayerDefinition;
+    use crate::defs::tileset_definition::TilesetDefinition;
+    use crate::exports::field_instance::FieldInstanceValue;
+
+    const PROJECT: &str = include_str!("../tests/fixtures/ldtk_1_5_3.ldtk");
+    const LEVEL: &str = "d5ab0642-8990-11ee-a83b-b50c1be5a2ca";
+    const TREES: &str = "dd021530-8990-11ee-a89b-e97b64798add";
+    const ENTITIES: &str = "be93eb10-8990-11ee-a83b-5163a689723a";
+    const GROUND: &str = "f45ab4f0-8990-11ee-a83b-9f796ddccd70";
+    const THIEF: &str = "add49da0-8990-11ee-a89b-6b5df8d8b196";
+    const AXE_MAN: &str = "036861a0-25d0-11ef-bafe-9b6d09a006ae";
+    const THIEF_2: &str = "47205270-25d0-11ef-bafe-05b804b5f562";
+
+    fn layer_instance<'a>(project: &'a ldtk::LdtkJson, iid: &str) -> &'a ldtk::LayerInstance {
+        project.levels[0]
+            .layer_instances
+            .iter()
+            .flatten()
+            .find(|layer_instance| layer_instance.iid == iid)
+            .unwrap()
+    }
+
+    fn spawn(world: &mut World, iid: &str, bundle: impl Bundle) {
+        let entity = world.spawn(bundle).id();
+        world.resource_mut::<IidMap>().insert(
+            Iid::new(iid),
+            entity,
+            &Handle::<LevelAsset>::default(),
+        );
+    }
+
+    #[test]
+    fn exports_runtime_changes() {
+        let mut source: ldtk::LdtkJson = serde_json::from_str(PROJECT).unwrap();
+        let project_iid = source.iid.clone();
+        let loaded_ground = layer_instance(&source, GROUND).clone();
+        let mut level_field = FieldInstance::new(&source.levels[0].field_instances[0]).unwrap();
+
+        // The fixture only lists the first thief
+        let mut thief_2_data = source.toc[0].instances_data[0].clone();
+        thief_2_data.iids.entity_iid = THIEF_2.to_owned();
+        source.toc[0].instances_data.push(thief_2_data);
+
+        let mut world = World::new();
+        world.init_resource::<IidMap>();
+        world.init_resource::<Assets<ProjectAsset>>();
+        world.init_resource::<Assets<LayerAsset>>();
+
+        let handle = world
+            .resource_mut::<Assets<ProjectAsset>>()
+            .add(ProjectAsset {
+                bg_color: Color::BLACK,
+                external_levels: false,
+                iid: project_iid.clone(),
+                json_version: source.json_version.clone(),
+                toc: Vec::new(),
+                tileset_assets: HashMap::default(),
+                background_assets: HashMap::default(),
+                layer_defs: source
+                    .defs
+                    .layers
+                    .iter()
+                    .map(|layer_def| (layer_def.uid, LayerDefinition::new(layer_def).unwrap()))
+                    .collect(),
+                entity_defs: HashMap::default(),
+                tileset_defs: source
+                    .defs
+                    .tilesets
+                    .iter()
+                    .map(|tileset_def| (tileset_def.uid, TilesetDefinition::new(tileset_def)))
+                    .collect(),
+                enum_defs: HashMap::default(),
+                world_handles: Vec::new(),
+                source: Some(source),
+            });
+        let entity = world.spawn_empty().id();
+        world
+            .resource_mut::<IidMap>()
+            .insert(Iid::new(&project_iid), entity, &handle);
+
+        level_field.value = FieldInstanceValue::Int(Some(7));
+        spawn(
+            &mut world,
+            LEVEL,
+            FieldInstances {
+                field_instances: vec![level_field],
+            },
+        );
+
+        spawn(
+            &mut world,
+            TREES,
+            Tiles {
+                tiles: vec![TileInstance {
+                    alpha: 1.0,
+                    flip_h: true,
+                    flip_v: true,
+                    location: I64Vec2::new(32, 48),
+                    source: UVec2::new(16, 0),
+                    tileset_id: 1,
+                }],
+            },
+        );
+
+        // Edited without auto_layer_system running since, so without an AutoLayerCache
+        let ground_handle = world.resource_mut::<Assets<LayerAsset>>().add(
+            LayerAsset::new(
+                &loaded_ground,
+                3,
+                LayerType::IntGrid,
+                Vec::new(),
+                Vec::new(),
+                0.0,
+                LEVEL.to_owned(),
+                project_iid.clone(),
+            )
+            .unwrap(),
+        );
+        let mut values = loaded_ground.int_grid_csv.clone();
+        values[0] = 0;
+        spawn(
+            &mut world,
+            GROUND,
+            (
+                ground_handle,
+                IntGrid {
+                    grid_size: I64Vec2::new(loaded_ground.c_wid, loaded_ground.c_hei),
+                    grid_cell_size: loaded_ground.grid_size,
+                    values: values.clone(),
+                    int_grid_values: Vec::new(),
+                    int_grid_values_groups: Vec::new(),
+                },
+            ),
+        );
+
+        // The second thief was killed
+        world.insert_resource(LdtkSnapshot {
+            despawned: [THIEF_2.to_owned()].into(),
+            ..default()
+        });
+        spawn(&mut world, ENTITIES, ());
+        spawn(&mut world, THIEF, Transform::from_xyz(40.0, -100.0, 0.0));
+        spawn(&mut world, AXE_MAN, Transform::from_xyz(120.0, -136.0, 0.0));
+
+        let export = export_ldtk_project(&world, &project_iid).unwrap();
+        assert!(export.external_levels.is_empty());
+
+        let project: ldtk::LdtkJson = serde_json::from_str(&export.project).unwrap();
+        assert_eq!(project.json_version, "1.5.3");
+        assert_eq!(
+            project.levels[0].field_instances[0].value,
+            Some(serde_json::json!(7))
+        );
+
+        let trees = layer_instance(&project, TREES);
+        assert_eq!(trees.grid_tiles.len(), 1);
+        assert_eq!(trees.grid_tiles[0].d, vec![2 + 3 * trees.c_wid]);
+        assert_eq!(trees.grid_tiles[0].f, 3);
+        assert_eq!(trees.grid_tiles[0].src, vec![16, 0]);
+
+        let ground = layer_instance(&project, GROUND);
+        assert_eq!(ground.int_grid_csv, values);
+        assert_ne!(ground.auto_layer_tiles, loaded_ground.auto_layer_tiles);
+        assert!(loaded_ground
+            .auto_layer_tiles
+            .iter()
+            .any(|tile| tile.d[1] == 0));
+        assert!(!ground.auto_layer_tiles.iter().any(|tile| tile.d[1] == 0));
+
+        // Rules are 3x3, so only the cells next to the edited one can change
+        let untouched = |tiles: &[ldtk::TileInstance]| {
+            tiles
+                .iter()
+                .filter(|tile| ![0, 1, 16, 17].contains(&tile.d[1]))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            untouched(&ground.auto_layer_tiles),
+            untouched(&loaded_ground.auto_layer_tiles)
+        );
+
+        let entities = layer_instance(&project, ENTITIES);
+        let iids = entities
+            .entity_instances
+            .iter()
+            .map(|entity_instance| entity_instance.iid.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(iids, vec![THIEF, AXE_MAN]);
+
+        let thief = &entities.entity_instances[0];
+        assert_eq!(thief.px, vec![40, 100]);
+        assert_eq!(thief.grid, vec![2, 6]);
+        assert_eq!(thief.world_x, Some(-168 - 48));
+
+        let toc = &project.toc[0].instances_data;
+        assert_eq!(toc.len(), 1);
+        assert_eq!(toc[0].iids.entity_iid, THIEF);
+        assert_eq!((toc[0].world_x, toc[0].world_y), (-168 - 48, 56 - 84));
+
+        let axe_man = &entities.entity_instances[1];
+        assert_eq!(axe_man.px, vec![120, 136]);
+        assert_eq!(axe_man.grid, vec![7, 8]);
+
+        // Entities that aren't spawned, without having been despawned, are kept
+        world.resource_mut::<LdtkSnapshot>().despawned.clear();
+        let export = export_ldtk_project(&world, &project_iid).unwrap();
+        let project: ldtk::LdtkJson = serde_json::from_str(&export.project).unwrap();
+        let iids = layer_instance(&project, ENTITIES)
+            .entity_instances
+            .iter()
+            .map(|entity_instance| entity_instance.iid.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(iids, vec![THIEF, AXE_MAN, THIEF_2]);
+    }
+}
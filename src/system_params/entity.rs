@@ -0,0 +1,73 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::assets::entity::EntityAsset;
+
+#[derive(SystemParam)]
+pub struct LdtkEntityCommands<'w, 's> {
+    entity_assets: Res<'w, Assets<EntityAsset>>,
+    query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Handle<EntityAsset>,
+            &'static GlobalTransform,
+        ),
+    >,
+}
+
+impl<'w, 's> LdtkEntityCommands<'w, 's> {
+    pub fn iter(&self) -> impl Iterator<Item = &EntityAsset> {
+        self.entity_assets.iter().map(|(_, entity)| entity)
+    }
+
+    // Spawned entities only
+    pub fn iter_entities(&self) -> impl Iterator<Item = (Entity, &EntityAsset, &GlobalTransform)> {
+        self.query
+            .iter()
+            .filter_map(|(entity, handle, global_transform)| {
+                Some((entity, self.entity_assets.get(handle)?, global_transform))
+            })
+    }
+}
+
+pub trait LdtkEntityCommandsEx<'w>:
+    Iterator<Item = (Entity, &'w EntityAsset, &'w GlobalTransform)> + Sized
+{
+    fn with_iid(mut self, iid: &'w str) -> Option<Self::Item> {
+        self.find(|(_, entity_asset, _)| entity_asset.iid == iid)
+    }
+
+    fn with_identifier(self, identifier: &'w str) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, entity_asset, _)| entity_asset.identifier == identifier)
+    }
+
+    fn with_tag(self, tag: &'w str) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, entity_asset, _)| entity_asset.tags.iter().any(|inner| inner == tag))
+    }
+
+    fn with_def_uid(self, def_uid: i64) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, entity_asset, _)| entity_asset.def_uid == def_uid)
+    }
+
+    fn in_layer(self, layer_iid: &'w str) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, entity_asset, _)| entity_asset.layer_iid == layer_iid)
+    }
+
+    fn in_level(self, level_iid: &'w str) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, entity_asset, _)| entity_asset.level_iid == level_iid)
+    }
+
+    // Entities whose origin lies within the world-space rectangle
+    fn within_rect(self, rect: Rect) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, _, global_transform)| {
+            rect.contains(global_transform.translation().truncate())
+        })
+    }
+}
+
+impl<'w, I: Iterator<Item = (Entity, &'w EntityAsset, &'w GlobalTransform)>>
+    LdtkEntityCommandsEx<'w> for I
+{
+}
@@ -0,0 +1,63 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::assets::layer::LayerAsset;
+use crate::assets::layer::LayerType;
+
+#[derive(SystemParam)]
+pub struct LdtkLayerCommands<'w, 's> {
+    layer_assets: Res<'w, Assets<LayerAsset>>,
+    query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Handle<LayerAsset>,
+            &'static GlobalTransform,
+        ),
+    >,
+}
+
+impl<'w, 's> LdtkLayerCommands<'w, 's> {
+    pub fn iter(&self) -> impl Iterator<Item = &LayerAsset> {
+        self.layer_assets.iter().map(|(_, layer)| layer)
+    }
+
+    // Spawned layers only
+    pub fn iter_entities(&self) -> impl Iterator<Item = (Entity, &LayerAsset, &GlobalTransform)> {
+        self.query
+            .iter()
+            .filter_map(|(entity, handle, global_transform)| {
+                Some((entity, self.layer_assets.get(handle)?, global_transform))
+            })
+    }
+}
+
+pub trait LdtkLayerCommandsEx<'w>:
+    Iterator<Item = (Entity, &'w LayerAsset, &'w GlobalTransform)> + Sized
+{
+    fn with_iid(mut self, iid: &'w str) -> Option<Self::Item> {
+        self.find(|(_, layer_asset, _)| layer_asset.iid == iid)
+    }
+
+    fn with_identifier(self, identifier: &'w str) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, layer_asset, _)| layer_asset.identifier == identifier)
+    }
+
+    fn with_layer_type(self, layer_type: LayerType) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, layer_asset, _)| layer_asset.layer_type == layer_type)
+    }
+
+    fn with_def_uid(self, layer_def_uid: i64) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, layer_asset, _)| layer_asset.layer_def_uid == layer_def_uid)
+    }
+
+    fn in_level(self, level_iid: &'w str) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, layer_asset, _)| layer_asset.level_iid == level_iid)
+    }
+}
+
+impl<'w, I: Iterator<Item = (Entity, &'w LayerAsset, &'w GlobalTransform)>> LdtkLayerCommandsEx<'w>
+    for I
+{
+}
@@ -0,0 +1,115 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::assets::level::LevelAsset;
+use crate::assets::world::WorldAsset;
+use crate::exports::neighbors::NeighbourDir;
+
+// Level lookups by location and by neighbour, including levels that aren't spawned. Locations are
+// in the project's space, which is Bevy's world space as long as the project entity is at the
+// origin.
+#[derive(SystemParam)]
+pub struct LdtkLevelCommands<'w, 's> {
+    world_assets: Res<'w, Assets<WorldAsset>>,
+    level_assets: Res<'w, Assets<LevelAsset>>,
+    query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Handle<LevelAsset>,
+            &'static GlobalTransform,
+        ),
+    >,
+}
+
+impl<'w, 's> LdtkLevelCommands<'w, 's> {
+    pub fn iter(&self) -> impl Iterator<Item = &LevelAsset> {
+        self.world_assets
+            .iter()
+            .flat_map(|(_, world_asset)| world_asset.level_handles.iter())
+            .flat_map(|handle| self.level_assets.get(handle))
+    }
+
+    // Spawned levels only
+    pub fn iter_entities(&self) -> impl Iterator<Item = (Entity, &LevelAsset, &GlobalTransform)> {
+        self.query
+            .iter()
+            .filter_map(|(entity, handle, global_transform)| {
+                Some((entity, self.level_assets.get(handle)?, global_transform))
+            })
+    }
+
+    pub fn with_iid(&self, iid: &str) -> Option<&LevelAsset> {
+        self.iter().find(|level_asset| level_asset.iid == iid)
+    }
+
+    pub fn with_identifier(&self, identifier: &str) -> Option<&LevelAsset> {
+        self.iter()
+            .find(|level_asset| level_asset.identifier == identifier)
+    }
+
+    // Every level containing the point, in any world and at any depth
+    pub fn levels_at(&self, point: Vec2) -> impl Iterator<Item = &LevelAsset> {
+        self.iter()
+            .filter(move |level_asset| level_asset.contains(point))
+    }
+
+    pub fn level_at(&self, point: Vec2) -> Option<&LevelAsset> {
+        self.levels_at(point).next()
+    }
+
+    pub fn neighbours(&self, iid: &str) -> impl Iterator<Item = (NeighbourDir, &LevelAsset)> {
+        self.with_iid(iid)
+            .into_iter()
+            .flat_map(|level_asset| level_asset.neighbours.iter())
+            .flat_map(|neighbour| {
+                self.with_iid(&neighbour.level_iid)
+                    .map(|level_asset| (neighbour.dir, level_asset))
+            })
+    }
+
+    // The first neighbour of the level in the given direction
+    pub fn neighbour(&self, iid: &str, dir: NeighbourDir) -> Option<&LevelAsset> {
+        self.neighbours(iid)
+            .find(|(neighbour_dir, _)| *neighbour_dir == dir)
+            .map(|(_, level_asset)| level_asset)
+    }
+
+    // The neighbour of the level containing the point, if any. Useful for finding the room an
+    // entity is about to walk into.
+    pub fn neighbour_at(&self, iid: &str, point: Vec2) -> Option<&LevelAsset> {
+        self.neighbours(iid)
+            .map(|(_, level_asset)| level_asset)
+            .find(|level_asset| level_asset.contains(point))
+    }
+}
+
+pub trait LdtkLevelCommandsEx<'w>:
+    Iterator<Item = (Entity, &'w LevelAsset, &'w GlobalTransform)> + Sized
+{
+    fn with_iid(mut self, iid: &'w str) -> Option<Self::Item> {
+        self.find(|(_, level_asset, _)| level_asset.iid == iid)
+    }
+
+    fn with_identifier(self, identifier: &'w str) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, level_asset, _)| level_asset.identifier == identifier)
+    }
+
+    // Levels whose world-space bounds overlap the rectangle
+    fn within_rect(self, rect: Rect) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, level_asset, global_transform)| {
+            let top_left = global_transform.translation().truncate();
+            let bounds = Rect::from_corners(
+                top_left,
+                top_left + Vec2::new(level_asset.size.x, -level_asset.size.y),
+            );
+            !bounds.intersect(rect).is_empty()
+        })
+    }
+}
+
+impl<'w, I: Iterator<Item = (Entity, &'w LevelAsset, &'w GlobalTransform)>> LdtkLevelCommandsEx<'w>
+    for I
+{
+}
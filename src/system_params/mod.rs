@@ -1,2 +1,5 @@
+pub mod entity;
+pub mod layer;
+pub mod level;
 pub mod project;
-
+pub mod world;
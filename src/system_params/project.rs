@@ -3,12 +3,14 @@ use bevy::prelude::*;
 
 use crate::assets::project::ProjectAsset;
 use crate::components::iid::Iid;
+use crate::components::iid::IidMap;
 
 #[derive(SystemParam)]
 pub struct LdtkProjectCommands<'w, 's> {
     // commands: Commands<'w, 's>,
     // asset_server: Res<'w, AssetServer>,
     project_assets: Res<'w, Assets<ProjectAsset>>,
+    iid_map: Res<'w, IidMap>,
     query: Query<'w, 's, (Entity, &'static Handle<ProjectAsset>, &'static Iid)>,
 }
 
@@ -17,6 +19,14 @@ impl<'w, 's> LdtkProjectCommands<'w, 's> {
         self.project_assets.iter().map(|(_, project)| project)
     }
 
+    // Spawned projects are found through the IidMap, others by a linear search.
+    pub fn with_iid(&self, iid: &str) -> Option<&ProjectAsset> {
+        self.iid_map
+            .handle::<ProjectAsset>(iid)
+            .and_then(|handle| self.project_assets.get(handle))
+            .or_else(|| self.iter().find(|project_asset| project_asset.iid == iid))
+    }
+
     pub fn iter_entities(&self) -> impl Iterator<Item = (Entity, &ProjectAsset, &Iid)> {
         self.query.iter().map(|(entity, handle, iid)| {
             (
@@ -42,9 +52,9 @@ pub trait LdtkProjectCommandsEntityEx<'w>:
     fn with_iid(mut self, iid: &'w str) -> Option<(Entity, &'w ProjectAsset, &'w Iid)> {
         self.find(|(_, _, inner_iid)| {
             // inner_iid
-            //     .filter(|inner_iid| inner_iid.0 == iid)
+            //     .filter(|inner_iid| inner_iid.as_str() == iid)
             //     .map(|_| (entity, asset, inner_iid))
-            inner_iid.0 == iid
+            inner_iid.as_str() == iid
         })
     }
 }
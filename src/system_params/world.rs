@@ -0,0 +1,50 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::assets::world::WorldAsset;
+
+#[derive(SystemParam)]
+pub struct LdtkWorldCommands<'w, 's> {
+    world_assets: Res<'w, Assets<WorldAsset>>,
+    query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Handle<WorldAsset>,
+            &'static GlobalTransform,
+        ),
+    >,
+}
+
+impl<'w, 's> LdtkWorldCommands<'w, 's> {
+    pub fn iter(&self) -> impl Iterator<Item = &WorldAsset> {
+        self.world_assets.iter().map(|(_, world)| world)
+    }
+
+    // Spawned worlds only
+    pub fn iter_entities(&self) -> impl Iterator<Item = (Entity, &WorldAsset, &GlobalTransform)> {
+        self.query
+            .iter()
+            .filter_map(|(entity, handle, global_transform)| {
+                Some((entity, self.world_assets.get(handle)?, global_transform))
+            })
+    }
+}
+
+pub trait LdtkWorldCommandsEx<'w>:
+    Iterator<Item = (Entity, &'w WorldAsset, &'w GlobalTransform)> + Sized
+{
+    fn with_iid(mut self, iid: &'w str) -> Option<Self::Item> {
+        self.find(|(_, world_asset, _)| world_asset.iid == iid)
+    }
+
+    fn with_identifier(self, identifier: &'w str) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |(_, world_asset, _)| world_asset.identifier == identifier)
+    }
+}
+
+impl<'w, I: Iterator<Item = (Entity, &'w WorldAsset, &'w GlobalTransform)>> LdtkWorldCommandsEx<'w>
+    for I
+{
+}
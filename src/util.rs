@@ -50,6 +50,12 @@ pub(crate) fn bevy_color_from_ldtk(color: &str) -> Result<Color, ColorParseError
     ))
 }
 
+// The inverse of bevy_color_from_ldtk. Alpha is dropped.
+pub(crate) fn ldtk_color_from_bevy(color: &Color) -> String {
+    let [red, green, blue, _] = color.as_rgba_u8();
+    format!("#{red:02X}{green:02X}{blue:02X}")
+}
+
 #[derive(Debug, Error)]
 pub enum AnchorIntoError {
     #[error("Provided array not four numbers!")]